@@ -2,29 +2,34 @@ use std::cell::OnceCell;
 use std::sync::Arc;
 use std::time::Instant;
 
-use rustls::ClientConfig;
+use rustls::{ClientConfig, RootCertStore};
 use xx_core::async_std::sync::Mutex;
-use xx_core::debug;
 use xx_core::lazy_static::lazy_static;
+use xx_core::{debug, warn};
 
 use super::*;
 use crate::dns::Resolver;
 use crate::tls::certs::load_system_certs;
+use crate::tls::error::TlsError;
+use crate::tls::policy::TlsPolicy;
 
 #[derive(Clone)]
 struct GlobalData {
 	dns_resolver: Arc<Resolver>,
+	root_certs: Arc<RootCertStore>,
 	tls_client_config: Arc<ClientConfig>
 }
 
 #[derive(Clone)]
 struct ThreadLocalData {
 	dns_resolver: Arc<Resolver>,
+	root_certs: Arc<RootCertStore>,
 	tls_client_config: Arc<ClientConfig>
 }
 
 lazy_static! {
 	static ref GLOBAL_DATA: Mutex<Option<GlobalData>> = Mutex::new(None);
+	static ref TLS_POLICY: Mutex<TlsPolicy> = Mutex::new(TlsPolicy::new());
 }
 
 thread_local! {
@@ -39,11 +44,19 @@ async fn create_global_data() -> GlobalData {
 
 	let Join(certs, resolver) = join(load_system_certs(), Resolver::new()).await;
 
-	let certs = certs.expect("Failed to load certs");
+	let certs = Arc::new(certs.unwrap_or_else(|err| {
+		/* connections fail verification until custom roots are configured */
+		warn!("== Failed to load root certificates: {}", err.to_string());
+
+		RootCertStore::empty()
+	}));
 	let resolver = resolver.expect("Failed to initialize DNS resolver");
+	let policy = TLS_POLICY.lock().await.unwrap().clone();
 
-	let config = ClientConfig::builder()
-		.with_root_certificates(certs)
+	let config = policy
+		.client_builder()
+		.expect("Invalid TLS policy")
+		.with_root_certificates(certs.clone())
 		.with_no_client_auth();
 
 	debug!(
@@ -53,6 +66,7 @@ async fn create_global_data() -> GlobalData {
 
 	GlobalData {
 		dns_resolver: Arc::new(resolver),
+		root_certs: certs,
 		tls_client_config: Arc::new(config)
 	}
 }
@@ -74,6 +88,7 @@ async fn create_thread_local_data() -> ThreadLocalData {
 
 	ThreadLocalData {
 		dns_resolver: data.dns_resolver,
+		root_certs: data.root_certs,
 		tls_client_config: data.tls_client_config
 	}
 }
@@ -110,11 +125,39 @@ pub fn root_certs_path() -> &'static str {
 	"/etc/ssl/certs"
 }
 
+/// Restricts the TLS versions, cipher suites and key exchange groups of the
+/// shared default client config. Fails with [`TlsError::DefaultConfigInUse`]
+/// once the shared config is built, so call this before making any
+/// connections
+///
+/// [`TlsError::DefaultConfigInUse`]: crate::tls::error::TlsError::DefaultConfigInUse
+#[asynchronous]
+pub async fn set_default_tls_policy(policy: TlsPolicy) -> Result<()> {
+	policy.resolve()?;
+
+	/* held while replacing the policy, so the config can't be built meanwhile */
+	let global = GLOBAL_DATA.lock().await.unwrap();
+
+	if global.is_some() {
+		return Err(TlsError::DefaultConfigInUse.into());
+	}
+
+	*TLS_POLICY.lock().await.unwrap() = policy;
+
+	Ok(())
+}
+
 #[asynchronous]
 pub async fn get_tls_client_config() -> Arc<ClientConfig> {
 	get_data().await.tls_client_config
 }
 
+/// The system root certificates, shared by every default client config
+#[asynchronous]
+pub async fn get_root_certs() -> Arc<RootCertStore> {
+	get_data().await.root_certs
+}
+
 #[asynchronous]
 pub async fn get_resolver() -> Arc<Resolver> {
 	get_data().await.dns_resolver
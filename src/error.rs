@@ -29,6 +29,10 @@ pub enum UrlError {
 	#[kind = ErrorKind::InvalidData]
 	RedirectForbidden(String),
 
+	#[display("Stream is not seekable")]
+	#[kind = ErrorKind::InvalidInput]
+	NotSeekable,
+
 	#[display("DNS query timed out")]
 	#[kind = ErrorKind::TimedOut]
 	DnsTimedOut
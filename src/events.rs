@@ -0,0 +1,58 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ::http::StatusCode;
+use rustls::{CipherSuite, ProtocolVersion};
+
+use super::*;
+use crate::http::Headers;
+
+/// A stage of a connection or request, reported to an [`EventListener`]
+#[derive(Debug)]
+pub enum Event<'a> {
+	/// Resolving `host` started
+	DnsStart { host: &'a str },
+
+	/// Resolving `host` finished, failing if `error` is set
+	DnsEnd {
+		host: &'a str,
+		addresses: usize,
+		elapsed: Duration,
+		error: Option<&'a Error>
+	},
+
+	/// A connection attempt to `addr` started
+	ConnectStart { addr: SocketAddr, attempt: u32 },
+
+	/// A connection attempt to `addr` finished, failing if `error` is set
+	ConnectEnd { addr: SocketAddr, elapsed: Duration, error: Option<&'a Error> },
+
+	/// The TLS handshake finished, failing if `error` is set
+	TlsHandshake {
+		version: Option<ProtocolVersion>,
+		cipher: Option<CipherSuite>,
+		elapsed: Duration,
+		error: Option<&'a Error>
+	},
+
+	/// The request line and headers were written
+	RequestSent { url: &'a Url, headers: &'a Headers },
+
+	/// The first byte of the response was received
+	FirstByte { elapsed: Duration },
+
+	/// A redirect from `from` to `to` is being followed
+	Redirect { from: &'a Url, to: &'a Url, status: StatusCode },
+
+	/// The response body was read completely
+	BodyComplete { bytes: u64, elapsed: Duration }
+}
+
+pub type EventListener = Arc<dyn Fn(&Event<'_>) + Send + Sync>;
+
+pub(crate) fn emit(listener: Option<&EventListener>, event: Event<'_>) {
+	if let Some(listener) = listener {
+		listener(&event);
+	}
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use xx_core::coroutines::Task;
 
 use super::*;
@@ -5,7 +7,8 @@ use super::*;
 pub struct Request {
 	pub(super) inner: RequestBase,
 	pub(super) start: Option<u64>,
-	pub(super) end: Option<u64>
+	pub(super) end: Option<u64>,
+	pub(super) progress: Option<ProgressCallback>
 }
 
 impl Request {
@@ -15,7 +18,8 @@ impl Request {
 		Self {
 			inner: RequestBase::new(url, |scheme| scheme == "file"),
 			start: None,
-			end: None
+			end: None,
+			progress: None
 		}
 	}
 
@@ -29,6 +33,15 @@ impl Request {
 		self
 	}
 
+	/// Call `callback` as the file is read
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn on_progress(
+		&mut self, callback: impl Fn(&Progress) + Send + Sync + 'static
+	) -> &mut Self {
+		self.progress = Some(Arc::new(callback));
+		self
+	}
+
 	#[asynchronous]
 	pub async fn run(&mut self) -> Result<FileStream> {
 		FileStream::new(self).await
@@ -8,7 +8,8 @@ use super::*;
 pub struct FileStream {
 	file: File,
 	start: u64,
-	end: u64
+	end: u64,
+	progress: Option<ProgressTracker>
 }
 
 #[asynchronous]
@@ -30,7 +31,13 @@ impl FileStream {
 		end = end.max(start);
 		file.seek(SeekFrom::Start(start)).await?;
 
-		Ok(Self { file, start, end })
+		#[allow(clippy::arithmetic_side_effects)]
+		let progress = request
+			.progress
+			.clone()
+			.map(|callback| ProgressTracker::new(callback, Some(end - start)));
+
+		Ok(Self { file, start, end, progress })
 	}
 
 	#[must_use]
@@ -54,7 +61,15 @@ impl Read for FileStream {
 
 		read_into!(buf, remaining.try_into().unwrap_or(usize::MAX));
 
-		self.file.read(buf).await
+		let read = self.file.read(buf).await?;
+
+		if read > 0 {
+			if let Some(progress) = &mut self.progress {
+				progress.update(read);
+			}
+		}
+
+		Ok(read)
 	}
 }
 
@@ -70,7 +85,14 @@ impl Seek for FileStream {
 
 		let pos = pos.clamp(self.start, self.end);
 
-		self.file.seek(SeekFrom::Start(pos)).await
+		let result = self.file.seek(SeekFrom::Start(pos)).await?;
+		let pos = self.pos();
+
+		if let Some(progress) = &mut self.progress {
+			progress.reset(pos);
+		}
+
+		Ok(result)
 	}
 
 	fn stream_len_fast(&self) -> bool {
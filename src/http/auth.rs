@@ -0,0 +1,472 @@
+use std::str::from_utf8;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crypto::digest::Digest;
+use crypto::md5::Md5;
+use crypto::sha2::Sha256;
+use url::Position;
+
+use super::*;
+
+#[derive(Clone)]
+pub enum Credentials {
+	Basic { username: String, password: String },
+	Bearer(String),
+	Digest { username: String, password: String }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+	Md5,
+	Md5Sess,
+	Sha256,
+	Sha256Sess
+}
+
+impl Algorithm {
+	fn from_name(name: &str) -> Option<Self> {
+		const ALGORITHMS: [(&str, Algorithm); 4] = [
+			("MD5", Algorithm::Md5),
+			("MD5-sess", Algorithm::Md5Sess),
+			("SHA-256", Algorithm::Sha256),
+			("SHA-256-sess", Algorithm::Sha256Sess)
+		];
+
+		ALGORITHMS
+			.iter()
+			.find(|(alg, _)| alg.eq_ignore_ascii_case(name))
+			.map(|(_, alg)| *alg)
+	}
+
+	const fn as_str(self) -> &'static str {
+		match self {
+			Self::Md5 => "MD5",
+			Self::Md5Sess => "MD5-sess",
+			Self::Sha256 => "SHA-256",
+			Self::Sha256Sess => "SHA-256-sess"
+		}
+	}
+
+	const fn is_session(self) -> bool {
+		matches!(self, Self::Md5Sess | Self::Sha256Sess)
+	}
+
+	fn hash(self, data: &str) -> String {
+		let mut digest: Box<dyn Digest> = match self {
+			Self::Md5 | Self::Md5Sess => Box::new(Md5::new()),
+			Self::Sha256 | Self::Sha256Sess => Box::new(Sha256::new())
+		};
+
+		digest.input_str(data);
+		digest.result_str()
+	}
+}
+
+/// A parsed `Digest` challenge from a `WWW-Authenticate` header
+#[derive(Clone)]
+struct Challenge {
+	realm: String,
+	nonce: String,
+	opaque: Option<String>,
+	algorithm: Algorithm,
+	qop: bool,
+	stale: bool
+}
+
+/// Splits a list of auth-params off the front of `value`, stopping at the
+/// next challenge or the end of the header
+fn parse_params(mut value: &str) -> (Vec<(String, String)>, &str) {
+	let mut params = Vec::new();
+
+	loop {
+		value = value.trim_start_matches(|ch: char| ch == ',' || ch.is_ascii_whitespace());
+
+		let Some((key, rest)) = value.split_once('=') else {
+			break;
+		};
+
+		let key = key.trim();
+
+		if key.is_empty() || key.contains(|ch: char| ch.is_ascii_whitespace() || ch == ',') {
+			/* start of the next challenge */
+			break;
+		}
+
+		let rest = rest.trim_start();
+
+		let (param, next) = if let Some(quoted) = rest.strip_prefix('"') {
+			let mut result = String::new();
+			let mut chars = quoted.char_indices();
+			let mut end = quoted.len();
+
+			while let Some((index, ch)) = chars.next() {
+				match ch {
+					'\\' => {
+						if let Some((_, ch)) = chars.next() {
+							result.push(ch);
+						}
+					}
+
+					'"' => {
+						end = index;
+
+						break;
+					}
+
+					ch => result.push(ch)
+				}
+			}
+
+			(result, quoted.get(end.saturating_add(1)..).unwrap_or(""))
+		} else {
+			let end = rest.find(',').unwrap_or(rest.len());
+
+			(rest[0..end].trim_end().to_string(), &rest[end..])
+		};
+
+		params.push((key.to_ascii_lowercase(), param));
+		value = next;
+	}
+
+	(params, value)
+}
+
+impl Challenge {
+	fn parse(mut header: &str) -> Option<Self> {
+		loop {
+			header = header.trim_start_matches(|ch: char| ch == ',' || ch.is_ascii_whitespace());
+
+			if header.is_empty() {
+				break None;
+			}
+
+			let end = header
+				.find(|ch: char| ch.is_ascii_whitespace() || ch == ',')
+				.unwrap_or(header.len());
+			let (scheme, rest) = header.split_at(end);
+			let (params, rest) = parse_params(rest);
+
+			header = rest;
+
+			if !scheme.eq_ignore_ascii_case("digest") {
+				continue;
+			}
+
+			let param = |name: &str| {
+				params
+					.iter()
+					.find(|(key, _)| key == name)
+					.map(|(_, value)| value.clone())
+			};
+
+			let algorithm = match param("algorithm") {
+				Some(name) => match Algorithm::from_name(&name) {
+					Some(algorithm) => algorithm,
+					None => continue
+				},
+
+				None => Algorithm::Md5
+			};
+
+			let qop = match param("qop") {
+				Some(qop) => {
+					if !qop.split(',').any(|qop| qop.trim().eq_ignore_ascii_case("auth")) {
+						/* only auth is supported, auth-int requires hashing the body */
+						continue;
+					}
+
+					true
+				}
+
+				None => false
+			};
+
+			let Some(nonce) = param("nonce") else {
+				continue;
+			};
+
+			break Some(Self {
+				realm: param("realm").unwrap_or_default(),
+				nonce,
+				opaque: param("opaque"),
+				algorithm,
+				qop,
+				stale: param("stale").is_some_and(|stale| stale.eq_ignore_ascii_case("true"))
+			});
+		}
+	}
+}
+
+fn percent_decode(str: &str) -> String {
+	let mut bytes = Vec::with_capacity(str.len());
+	let mut input = str.as_bytes();
+
+	while let Some((&byte, rest)) = input.split_first() {
+		input = rest;
+
+		if byte == b'%' {
+			let hex = rest
+				.get(0..2)
+				.and_then(|hex| from_utf8(hex).ok())
+				.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+			if let Some(decoded) = hex {
+				bytes.push(decoded);
+				input = &rest[2..];
+
+				continue;
+			}
+		}
+
+		bytes.push(byte);
+	}
+
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn quote(value: &str) -> String {
+	let mut quoted = String::with_capacity(value.len().saturating_add(2));
+
+	quoted.push('"');
+
+	for ch in value.chars() {
+		if ch == '"' || ch == '\\' {
+			quoted.push('\\');
+		}
+
+		quoted.push(ch);
+	}
+
+	quoted.push('"');
+	quoted
+}
+
+pub(crate) struct Auth {
+	credentials: Credentials,
+	challenge: Option<Challenge>,
+	nonce_count: u32,
+	stale_retried: bool
+}
+
+impl Auth {
+	pub(crate) const fn new(credentials: Credentials) -> Self {
+		Self { credentials, challenge: None, nonce_count: 0, stale_retried: false }
+	}
+
+	/// Credentials embedded in the userinfo of `url`, if any
+	pub(crate) fn from_url(url: &Url) -> Option<Self> {
+		if url.username().is_empty() && url.password().is_none() {
+			return None;
+		}
+
+		Some(Self::new(Credentials::Basic {
+			username: percent_decode(url.username()),
+			password: url.password().map(percent_decode).unwrap_or_default()
+		}))
+	}
+
+	fn user_pass(&self) -> Option<(&str, &str)> {
+		match &self.credentials {
+			Credentials::Basic { username, password } |
+			Credentials::Digest { username, password } => Some((username, password)),
+			Credentials::Bearer(_) => None
+		}
+	}
+
+	fn digest(&mut self, method: &Method, uri: &str) -> Option<String> {
+		let (username, password) = self.user_pass()?;
+		let challenge = self.challenge.as_ref()?;
+		let algorithm = challenge.algorithm;
+		let nonce_count = self.nonce_count.wrapping_add(1);
+
+		let mut ha1 = algorithm.hash(&format!("{}:{}:{}", username, challenge.realm, password));
+		let ha2 = algorithm.hash(&format!("{}:{}", method.as_str(), uri));
+
+		let cnonce: String = rand::random::<[u8; 16]>()
+			.iter()
+			.map(|byte| format!("{:02x}", byte))
+			.collect();
+
+		if algorithm.is_session() {
+			ha1 = algorithm.hash(&format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+		}
+
+		let mut header = format!(
+			"Digest username={}, realm={}, nonce={}, uri={}, algorithm={}",
+			quote(username),
+			quote(&challenge.realm),
+			quote(&challenge.nonce),
+			quote(uri),
+			algorithm.as_str()
+		);
+
+		let response = if challenge.qop {
+			let nc = format!("{:08x}", nonce_count);
+
+			header.push_str(&format!(", qop=auth, nc={}, cnonce={}", nc, quote(&cnonce)));
+
+			algorithm.hash(&format!(
+				"{}:{}:{}:{}:auth:{}",
+				ha1, challenge.nonce, nc, cnonce, ha2
+			))
+		} else {
+			algorithm.hash(&format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+		};
+
+		header.push_str(&format!(", response={}", quote(&response)));
+
+		if let Some(opaque) = &challenge.opaque {
+			header.push_str(&format!(", opaque={}", quote(opaque)));
+		}
+
+		if challenge.qop {
+			self.nonce_count = nonce_count;
+		}
+
+		Some(header)
+	}
+
+	fn authorization(&mut self, method: &Method, url: &Url) -> Option<String> {
+		if let Credentials::Bearer(token) = &self.credentials {
+			return Some(format!("Bearer {}", token));
+		}
+
+		if self.challenge.is_none() {
+			/* digest credentials are only sent once challenged */
+			return match &self.credentials {
+				Credentials::Basic { username, password } => Some(format!(
+					"Basic {}",
+					STANDARD.encode(format!("{}:{}", username, password))
+				)),
+
+				_ => None
+			};
+		}
+
+		self.digest(method, &url[Position::BeforePath..Position::AfterQuery])
+	}
+
+	/// Sets the `Authorization` header for a request to `url`. Credentials
+	/// are only sent to the origin of the original request
+	pub(crate) fn authorize(
+		&mut self, headers: &mut Headers, method: &Method, url: &Url, origin: &Url
+	) -> Result<()> {
+		if url.origin() != origin.origin() {
+			headers.remove(header::AUTHORIZATION);
+
+			return Ok(());
+		}
+
+		match self.authorization(method, url) {
+			Some(value) => {
+				let mut value = value.as_str().try_into_value()?;
+
+				value.set_sensitive(true);
+				headers.insert(header::AUTHORIZATION, value)?;
+			}
+
+			None => {
+				headers.remove(header::AUTHORIZATION);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Handles a `401 Unauthorized` response. Returns true if the request
+	/// should be sent again with the new challenge
+	pub(crate) fn challenged(&mut self, headers: &Headers) -> Result<bool> {
+		if self.user_pass().is_none() {
+			return Ok(false);
+		}
+
+		let Some(value) = headers.get_str(header::WWW_AUTHENTICATE)? else {
+			return Ok(false);
+		};
+
+		let Some(challenge) = Challenge::parse(value) else {
+			return Ok(false);
+		};
+
+		/* a challenge was already answered, and the server rejected our
+		 * credentials instead of asking for a new nonce. like curl, a stale
+		 * nonce is only retried once so a broken server can't loop forever */
+		if self.challenge.is_some() {
+			if !challenge.stale || self.stale_retried {
+				return Ok(false);
+			}
+
+			self.stale_retried = true;
+		}
+
+		self.challenge = Some(challenge);
+		self.nonce_count = 0;
+
+		Ok(true)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn challenge_headers(value: &str) -> Headers {
+		let mut headers = Headers::new();
+
+		headers.insert(header::WWW_AUTHENTICATE, value).unwrap();
+		headers
+	}
+
+	#[test]
+	fn test_parse_challenge() {
+		let challenge = Challenge::parse(
+			"Basic realm=\"basic\", Digest realm=\"test \\\"realm\\\"\", qop=\"auth,auth-int\", \
+			 nonce=abc, opaque=\"xyz\", algorithm=SHA-256, stale=TRUE"
+		)
+		.unwrap();
+
+		assert_eq!(challenge.realm, "test \"realm\"");
+		assert_eq!(challenge.nonce, "abc");
+		assert_eq!(challenge.opaque.as_deref(), Some("xyz"));
+		assert!(challenge.algorithm == Algorithm::Sha256);
+		assert!(challenge.qop);
+		assert!(challenge.stale);
+	}
+
+	#[test]
+	fn test_parse_unsupported_challenge() {
+		assert!(Challenge::parse("Basic realm=\"basic\"").is_none());
+		assert!(Challenge::parse("Digest realm=\"r\"").is_none());
+		assert!(Challenge::parse("Digest nonce=\"n\", qop=\"auth-int\"").is_none());
+		assert!(Challenge::parse("Digest nonce=\"n\", algorithm=SHA-512").is_none());
+
+		let challenge = Challenge::parse("Digest nonce=\"n\"").unwrap();
+
+		assert!(challenge.algorithm == Algorithm::Md5);
+		assert!(!challenge.qop && !challenge.stale);
+	}
+
+	#[test]
+	fn test_stale_retried_once() {
+		let mut auth = Auth::new(Credentials::Digest {
+			username: "user".to_string(),
+			password: "pass".to_string()
+		});
+
+		let fresh = challenge_headers("Digest realm=\"r\", nonce=\"1\"");
+		let stale = challenge_headers("Digest realm=\"r\", nonce=\"2\", stale=true");
+
+		assert!(auth.challenged(&fresh).unwrap());
+		assert!(auth.challenged(&stale).unwrap());
+		assert!(!auth.challenged(&stale).unwrap());
+		assert!(!auth.challenged(&fresh).unwrap());
+	}
+
+	#[test]
+	fn test_bearer_not_challenged() {
+		let mut auth = Auth::new(Credentials::Bearer("token".to_string()));
+
+		assert!(!auth.challenged(&challenge_headers("Digest nonce=\"n\"")).unwrap());
+	}
+}
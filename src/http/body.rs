@@ -29,21 +29,73 @@ enum Transfer {
 	Trailers
 }
 
+/// Receives the data of a body as it is read
+pub(crate) trait BodyObserver: Send + Sync {
+	fn data(&mut self, data: &[u8]);
+
+	fn complete(&mut self);
+}
+
+/// Reports the end of the body to an [`EventListener`]
+struct BodyEvents {
+	listener: EventListener,
+	bytes: u64,
+	start: Instant
+}
+
+impl BodyObserver for BodyEvents {
+	fn data(&mut self, data: &[u8]) {
+		self.bytes = self.bytes.saturating_add(data.len() as u64);
+	}
+
+	fn complete(&mut self) {
+		emit(
+			Some(&self.listener),
+			Event::BodyComplete { bytes: self.bytes, elapsed: self.start.elapsed() }
+		);
+	}
+}
+
 pub struct Body {
 	reader: BufReader<HttpConn>,
 	transfer: Transfer,
-	reusable: bool
+	reusable: bool,
+	mode: ParseMode,
+	observers: Vec<Box<dyn BodyObserver>>,
+	max_size: Option<u64>,
+	rate: Option<RateLimiter>,
+	progress: Option<ProgressTracker>,
+	bytes_read: u64,
+	started: Instant,
+	finished: Option<Duration>
 }
 
 #[asynchronous]
 impl Body {
 	pub(super) fn new(
 		reader: BufReader<HttpConn>, request: &Request, response: &RawResponse
+	) -> Result<Self> {
+		Self::with_max_size(reader, request, response, request.options.max_body_size)
+	}
+
+	/// Creates a body limited to `max_size` bytes instead of the request's
+	/// `max_body_size`
+	pub(super) fn with_max_size(
+		reader: BufReader<HttpConn>, request: &Request, response: &RawResponse,
+		max_size: Option<u64>
 	) -> Result<Self> {
 		let mut body = Self {
 			reader,
 			transfer: Transfer::Connection,
-			reusable: false
+			reusable: false,
+			mode: request.options.parse_mode,
+			observers: Vec::new(),
+			max_size,
+			rate: request.options.download_rate.map(RateLimiter::new),
+			progress: None,
+			bytes_read: 0,
+			started: Instant::now(),
+			finished: None
 		};
 
 		let bodyless = match (&request.method, response.status.as_u16()) {
@@ -52,6 +104,14 @@ impl Body {
 			(_, code) => (100..200).contains(&code)
 		};
 
+		let has_encoding = response.headers.get(header::TRANSFER_ENCODING).is_some();
+		let has_length = response.headers.get(header::CONTENT_LENGTH).is_some();
+		let conflicting = !bodyless && has_encoding && has_length;
+
+		if conflicting && body.mode == ParseMode::Strict {
+			return Err(HttpError::ConflictingLength.into());
+		}
+
 		if bodyless {
 			body.transfer = Transfer::Empty;
 		} else if let Some(encoding) = response.headers.get_str(header::TRANSFER_ENCODING)? {
@@ -77,6 +137,34 @@ impl Body {
 			}
 		}
 
+		if conflicting {
+			warn!(
+				target: &body,
+				"== Both Content-Length and Transfer-Encoding present, ignoring Content-Length"
+			);
+
+			/* the server's framing is ambiguous, don't trust the connection afterwards */
+			body.reusable = false;
+		}
+
+		if let (Transfer::Length(len), Some(max_size)) = (&body.transfer, body.max_size) {
+			if *len > max_size {
+				return Err(HttpError::BodyTooLarge.into());
+			}
+		}
+
+		if let (Some(har), Some(index)) = (&request.har, response.har_entry) {
+			har.capture(&mut body, index);
+		}
+
+		if let Some(listener) = request.listener.clone() {
+			body.observe(Box::new(BodyEvents { listener, bytes: 0, start: Instant::now() }));
+		}
+
+		if let Some(callback) = request.download_progress.clone() {
+			body.progress = Some(ProgressTracker::new(callback, body.remaining()));
+		}
+
 		Ok(body)
 	}
 
@@ -208,7 +296,7 @@ impl Body {
 			"There is either is data left in the body or the stream has been exhausted"
 		);
 
-		let header = read_header_line_limited(&mut self.reader).await?;
+		let header = read_header_line_limited(&mut self.reader, self.mode).await?;
 
 		if header.is_none() {
 			self.transfer = Transfer::Empty;
@@ -233,6 +321,16 @@ impl Body {
 		Ok(headers)
 	}
 
+	/// The parse mode of the request this body belongs to
+	pub(super) const fn parse_mode(&self) -> ParseMode {
+		self.mode
+	}
+
+	/// How many more bytes may be read before exceeding the size limit
+	pub(super) const fn max_size_remaining(&self) -> Option<u64> {
+		self.max_size
+	}
+
 	#[must_use]
 	pub const fn remaining(&self) -> Option<u64> {
 		match self.transfer {
@@ -241,11 +339,43 @@ impl Body {
 			_ => None
 		}
 	}
-}
 
-#[asynchronous]
-impl Read for Body {
-	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+	/// Bytes read and time taken so far
+	#[must_use]
+	pub fn stats(&self) -> BodyStats {
+		BodyStats {
+			bytes: self.bytes_read,
+			duration: self.finished.unwrap_or_else(|| self.started.elapsed())
+		}
+	}
+
+	const fn is_complete(&self) -> bool {
+		matches!(self.transfer, Transfer::Empty | Transfer::Trailers)
+	}
+
+	pub(crate) fn observe(&mut self, mut observer: Box<dyn BodyObserver>) {
+		if self.is_complete() {
+			observer.complete();
+		} else {
+			self.observers.push(observer);
+		}
+	}
+
+	fn notify(&mut self, data: &[u8]) {
+		if !data.is_empty() {
+			for observer in &mut self.observers {
+				observer.data(data);
+			}
+		}
+
+		if self.is_complete() {
+			for mut observer in self.observers.drain(..) {
+				observer.complete();
+			}
+		}
+	}
+
+	async fn read_data(&mut self, buf: &mut [u8]) -> Result<usize> {
 		/* don't do read_into! here as it's done after calculating remaining bytes */
 		match &self.transfer {
 			Transfer::Empty | Transfer::Trailers => Ok(0),
@@ -289,3 +419,61 @@ impl Read for Body {
 		}
 	}
 }
+
+#[asynchronous]
+impl Read for Body {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		/* never copy more than the size limit allows into the caller's buffer */
+		let allowed = match self.max_size {
+			Some(remaining) => buf.len().min(remaining.try_into().unwrap_or(usize::MAX)),
+			None => buf.len()
+		};
+
+		if allowed == 0 && !buf.is_empty() && !self.is_complete() {
+			/* the limit is reached, any more data exceeds it */
+			if self.read_data(&mut [0u8; 1]).await? != 0 {
+				return Err(HttpError::BodyTooLarge.into());
+			}
+		}
+
+		let buf = &mut buf[0..allowed];
+		let limited = !buf.is_empty() && !self.is_complete();
+		let len = match &mut self.rate {
+			Some(rate) if limited => rate.acquire(buf.len()).await?,
+			_ => buf.len()
+		};
+
+		let result = self.read_data(&mut buf[0..len]).await;
+
+		if let Some(rate) = self.rate.as_mut().filter(|_| limited) {
+			#[allow(clippy::arithmetic_side_effects)]
+			rate.release(len - *result.as_ref().unwrap_or(&0));
+		}
+
+		let read = result?;
+
+		if let Some(remaining) = &mut self.max_size {
+			*remaining = remaining
+				.checked_sub(read as u64)
+				.ok_or(HttpError::BodyTooLarge)?;
+		}
+
+		self.bytes_read = self.bytes_read.saturating_add(read as u64);
+
+		if self.finished.is_none() && self.is_complete() {
+			self.finished = Some(self.started.elapsed());
+		}
+
+		if read > 0 {
+			if let Some(progress) = &mut self.progress {
+				progress.update(read);
+			}
+		}
+
+		if !self.observers.is_empty() {
+			self.notify(&buf[0..read]);
+		}
+
+		Ok(read)
+	}
+}
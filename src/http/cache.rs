@@ -0,0 +1,660 @@
+use std::collections::HashMap;
+use std::mem::take;
+use std::sync::{Arc, Mutex};
+
+use xx_core::enumflags2::BitFlags;
+use xx_core::os::epoll::PollFlag;
+use xx_core::os::socket::Shutdown;
+
+use super::*;
+
+const DEFAULT_CAPACITY: usize = 64 * 1024 * 1024;
+
+/* statuses that may be cached without explicit freshness information */
+const HEURISTIC_STATUSES: [u16; 12] = [200, 203, 204, 206, 300, 301, 308, 404, 405, 410, 414, 501];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CacheStatus {
+	/// Served from the cache without contacting the server
+	Hit,
+
+	/// Served stale from the cache as allowed by `stale-while-revalidate`.
+	/// No background revalidation is started, the entry is only refreshed by
+	/// a request made after the stale window ends
+	Stale,
+
+	/// The server confirmed the cached response is still valid
+	Revalidated,
+
+	/// The response came from the server
+	Miss
+}
+
+#[derive(Default)]
+struct CacheControl {
+	no_store: bool,
+	no_cache: bool,
+	public: bool,
+	private: bool,
+	must_revalidate: bool,
+	max_age: Option<u64>,
+	stale_while_revalidate: Option<u64>
+}
+
+impl CacheControl {
+	fn parse(headers: &Headers) -> Result<Self> {
+		let mut this = Self::default();
+
+		let Some(value) = headers.get_str(header::CACHE_CONTROL)? else {
+			return Ok(this);
+		};
+
+		for directive in value.split(',') {
+			let (name, arg) = match directive.split_once('=') {
+				Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+				None => (directive.trim(), None)
+			};
+
+			let seconds = || arg.and_then(|arg| arg.parse().ok());
+
+			match name.to_ascii_lowercase().as_str() {
+				"no-store" => this.no_store = true,
+				"no-cache" => this.no_cache = true,
+				"public" => this.public = true,
+				"private" => this.private = true,
+				"must-revalidate" | "proxy-revalidate" => this.must_revalidate = true,
+				"max-age" => this.max_age = seconds(),
+				"stale-while-revalidate" => this.stale_while_revalidate = seconds(),
+				_ => ()
+			}
+		}
+
+		Ok(this)
+	}
+}
+
+/// Whether a response may be stored as in RFC 9111 section 3. Validators
+/// alone don't allow it. `s-maxage` only applies to shared caches, and this
+/// one is private
+fn allows_storing(status: StatusCode, headers: &Headers, control: &CacheControl) -> bool {
+	control.max_age.is_some() ||
+		control.public ||
+		control.private ||
+		headers.contains_key(header::EXPIRES) ||
+		HEURISTIC_STATUSES.contains(&status.as_u16())
+}
+
+fn header_date(headers: &Headers, name: HeaderName) -> Option<SystemTime> {
+	let value = headers.get_str(name).ok()??;
+
+	httpdate::parse_http_date(value).ok()
+}
+
+fn since(later: SystemTime, earlier: SystemTime) -> Duration {
+	later.duration_since(earlier).unwrap_or(Duration::ZERO)
+}
+
+struct Entry {
+	vary: Vec<(HeaderName, Option<HeaderValue>)>,
+	version: Version,
+	status: StatusCode,
+	headers: Headers,
+	body: Arc<[u8]>,
+	request_time: SystemTime,
+	response_time: SystemTime,
+	stored: Instant
+}
+
+impl Entry {
+	fn matches(&self, headers: &Headers) -> bool {
+		self.vary
+			.iter()
+			.all(|(name, value)| headers.get(name.clone()) == value.as_ref())
+	}
+
+	/// The age of the response as calculated in RFC 9111 section 4.2.3
+	fn current_age(&self, now: SystemTime) -> Duration {
+		let age = self
+			.headers
+			.get_str(header::AGE)
+			.ok()
+			.flatten()
+			.and_then(|age| age.parse().ok())
+			.map(Duration::from_secs)
+			.unwrap_or_default();
+
+		let apparent_age = header_date(&self.headers, header::DATE)
+			.map(|date| since(self.response_time, date))
+			.unwrap_or_default();
+
+		let response_delay = since(self.response_time, self.request_time);
+		let corrected_age = age.saturating_add(response_delay);
+		let initial_age = apparent_age.max(corrected_age);
+
+		initial_age.saturating_add(since(now, self.response_time))
+	}
+
+	/// The freshness lifetime as calculated in RFC 9111 section 4.2.1
+	fn freshness_lifetime(&self, control: &CacheControl) -> Duration {
+		if let Some(max_age) = control.max_age {
+			return Duration::from_secs(max_age);
+		}
+
+		let date = header_date(&self.headers, header::DATE).unwrap_or(self.response_time);
+
+		if self.headers.contains_key(header::EXPIRES) {
+			/* invalid dates represent a time in the past */
+			return header_date(&self.headers, header::EXPIRES)
+				.map(|expires| since(expires, date))
+				.unwrap_or_default();
+		}
+
+		if !HEURISTIC_STATUSES.contains(&self.status.as_u16()) {
+			return Duration::ZERO;
+		}
+
+		header_date(&self.headers, header::LAST_MODIFIED)
+			.map(|modified| since(date, modified) / 10)
+			.unwrap_or_default()
+	}
+}
+
+struct Store {
+	entries: HashMap<String, Vec<Arc<Entry>>>,
+	size: usize,
+	capacity: usize
+}
+
+impl Store {
+	fn evict(&mut self) {
+		while self.size > self.capacity {
+			let oldest = self
+				.entries
+				.iter()
+				.flat_map(|(key, entries)| entries.iter().map(move |entry| (key, entry)))
+				.min_by_key(|(_, entry)| entry.stored)
+				.map(|(key, entry)| (key.clone(), entry.clone()));
+
+			let Some((key, entry)) = oldest else {
+				break;
+			};
+
+			self.remove_entry(&key, &entry);
+		}
+	}
+
+	fn remove_entry(&mut self, key: &str, entry: &Arc<Entry>) {
+		let Some(entries) = self.entries.get_mut(key) else {
+			return;
+		};
+
+		entries.retain(|existing| {
+			if !Arc::ptr_eq(existing, entry) {
+				return true;
+			}
+
+			self.size = self.size.saturating_sub(existing.body.len());
+
+			false
+		});
+
+		if entries.is_empty() {
+			self.entries.remove(key);
+		}
+	}
+
+	fn insert(&mut self, key: String, entry: Entry) -> Arc<Entry> {
+		let entry = Arc::new(entry);
+		let entries = self.entries.entry(key).or_default();
+
+		/* replace any entry with the same secondary key */
+		entries.retain(|existing| {
+			if existing.vary != entry.vary {
+				return true;
+			}
+
+			self.size = self.size.saturating_sub(existing.body.len());
+
+			false
+		});
+
+		entries.push(entry.clone());
+
+		self.size = self.size.saturating_add(entry.body.len());
+		self.evict();
+
+		entry
+	}
+
+	fn remove(&mut self, key: &str) {
+		if let Some(entries) = self.entries.remove(key) {
+			for entry in entries {
+				self.size = self.size.saturating_sub(entry.body.len());
+			}
+		}
+	}
+}
+
+/// Stores the body of a response in the cache once it has been read
+struct Capture {
+	store: Arc<Mutex<Store>>,
+	key: String,
+	entry: Option<Entry>,
+	data: Vec<u8>,
+	limit: usize
+}
+
+impl BodyObserver for Capture {
+	fn data(&mut self, data: &[u8]) {
+		if self.entry.is_none() {
+			return;
+		}
+
+		if self.data.len().saturating_add(data.len()) > self.limit {
+			self.entry = None;
+			self.data = Vec::new();
+		} else {
+			self.data.extend_from_slice(data);
+		}
+	}
+
+	fn complete(&mut self) {
+		let Some(mut entry) = self.entry.take() else {
+			return;
+		};
+
+		entry.body = take(&mut self.data).into();
+		entry.stored = Instant::now();
+
+		let _ = entry
+			.headers
+			.insert(header::CONTENT_LENGTH, entry.body.len().to_string().as_str());
+
+		if let Ok(mut store) = self.store.lock() {
+			store.insert(take(&mut self.key), entry);
+		}
+	}
+}
+
+/// A body backed by a cached response
+struct CachedConn {
+	data: Arc<[u8]>,
+	offset: usize
+}
+
+#[asynchronous]
+impl Read for CachedConn {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		let read = read_into_slice(buf, &self.data[self.offset..]);
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(self.offset += read);
+
+		Ok(read)
+	}
+}
+
+#[asynchronous]
+impl Write for CachedConn {
+	async fn write(&mut self, _: &[u8]) -> Result<usize> {
+		Err(ErrorKind::Shutdown.into())
+	}
+}
+
+#[asynchronous]
+impl ConnExtra for CachedConn {
+	async fn poll(&mut self, flags: BitFlags<PollFlag>) -> Result<BitFlags<PollFlag>> {
+		Ok(flags)
+	}
+
+	async fn shutdown(&mut self, _: Shutdown) -> Result<()> {
+		Ok(())
+	}
+}
+
+#[asynchronous]
+impl Connection for CachedConn {
+	fn try_split(&mut self) -> Result<(HttpConnReadHalf<'_>, HttpConnWriteHalf<'_>)> {
+		Err(fmt_error!("Cannot split a cached response" @ ErrorKind::InvalidInput))
+	}
+}
+
+/// An in-memory HTTP cache following RFC 9111, acting as a private cache.
+/// Clones share the same storage. Entries are not persisted to disk and are
+/// lost when the last clone is dropped
+///
+/// Responses within their `stale-while-revalidate` window are served stale
+/// without revalidating them in the background
+#[derive(Clone)]
+pub struct Cache {
+	store: Arc<Mutex<Store>>
+}
+
+#[asynchronous]
+impl Cache {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::with_capacity(DEFAULT_CAPACITY)
+	}
+
+	/// Creates a cache holding at most `capacity` bytes of response bodies
+	#[must_use]
+	pub fn with_capacity(capacity: usize) -> Self {
+		let store = Store { entries: HashMap::new(), size: 0, capacity };
+
+		Self { store: Arc::new(Mutex::new(store)) }
+	}
+
+	pub fn clear(&self) {
+		if let Ok(mut store) = self.store.lock() {
+			store.entries.clear();
+			store.size = 0;
+		}
+	}
+
+	fn lookup(&self, key: &str, headers: &Headers) -> Option<Arc<Entry>> {
+		let store = self.store.lock().ok()?;
+
+		store
+			.entries
+			.get(key)?
+			.iter()
+			.find(|entry| entry.matches(headers))
+			.cloned()
+	}
+
+	fn invalidate(&self, key: &str) {
+		if let Ok(mut store) = self.store.lock() {
+			store.remove(key);
+		}
+	}
+
+	fn cached(
+		entry: &Entry, request: &Request, status: CacheStatus, now: SystemTime
+	) -> Result<(RawResponse, Body)> {
+		let mut headers = entry.headers.clone();
+
+		headers.insert(
+			header::AGE,
+			entry.current_age(now).as_secs().to_string().as_str()
+		)?;
+
+		let response = RawResponse {
+			stats: Stats { cache: Some(status), ..Default::default() },
+			tls: None,
+			hops: Vec::new(),
+			version: entry.version,
+			status: entry.status,
+			headers,
+			url: None,
+			har_entry: None
+		};
+
+		let conn = CachedConn { data: entry.body.clone(), offset: 0 };
+		let body = Body::new(BufReader::new(HttpConn::new(conn)), request, &response)?;
+
+		Ok((response, body))
+	}
+
+	fn storable(request: &Request, response: &RawResponse, control: &CacheControl) -> Result<bool> {
+		if response.url.is_some() ||
+			control.no_store ||
+			CacheControl::parse(&request.headers)?.no_store
+		{
+			return Ok(false);
+		}
+
+		if request.headers.contains_key(header::AUTHORIZATION) &&
+			!(control.public || control.must_revalidate)
+		{
+			return Ok(false);
+		}
+
+		if response
+			.headers
+			.get_str(header::VARY)?
+			.is_some_and(|vary| vary.split(',').any(|name| name.trim() == "*"))
+		{
+			return Ok(false);
+		}
+
+		Ok(allows_storing(response.status, &response.headers, control))
+	}
+
+	fn new_entry(
+		request: &Request, response: &RawResponse, request_time: SystemTime,
+		response_time: SystemTime
+	) -> Result<Entry> {
+		let mut vary = Vec::new();
+
+		if let Some(names) = response.headers.get_str(header::VARY)? {
+			for name in names.split(',') {
+				let name = name.trim().try_into_name()?;
+				let value = request.headers.get(name.clone()).cloned();
+
+				vary.push((name, value));
+			}
+		}
+
+		let mut headers = response.headers.clone();
+
+		/* the body is stored decoded */
+		headers.remove(header::TRANSFER_ENCODING);
+
+		Ok(Entry {
+			vary,
+			version: response.version,
+			status: response.status,
+			headers,
+			body: Arc::new([]),
+			request_time,
+			response_time,
+			stored: Instant::now()
+		})
+	}
+
+	async fn fetch_uncached(request: &mut Request) -> Result<(RawResponse, Body)> {
+		let (response, reader) = transfer(request, None).await?;
+
+		check_range(request, &response)?;
+
+		let body = Body::new(reader, request, &response)?;
+
+		Ok((response, body))
+	}
+
+	pub(super) async fn fetch(&self, request: &mut Request) -> Result<(RawResponse, Body)> {
+		let mut url = request.request.finalize()?.clone();
+
+		url.set_fragment(None);
+
+		let key = url.to_string();
+
+		if request.method != Method::GET || range_header(&request.options)?.is_some() {
+			let (mut response, body) = Self::fetch_uncached(request).await?;
+
+			if !matches!(
+				request.method,
+				Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+			) && (response.status.is_success() || response.status.is_redirection())
+			{
+				self.invalidate(&key);
+			}
+
+			response.stats.cache = Some(CacheStatus::Miss);
+
+			return Ok((response, body));
+		}
+
+		let request_control = CacheControl::parse(&request.headers)?;
+		let entry = if request_control.no_store {
+			None
+		} else {
+			self.lookup(&key, &request.headers)
+		};
+
+		let mut conditional = Vec::new();
+
+		if let Some(entry) = &entry {
+			let now = SystemTime::now();
+			let control = CacheControl::parse(&entry.headers)?;
+			let age = entry.current_age(now);
+			let lifetime = entry.freshness_lifetime(&control);
+
+			let acceptable = request_control
+				.max_age
+				.map_or(true, |max_age| age <= Duration::from_secs(max_age));
+
+			if !request_control.no_cache && !control.no_cache && acceptable {
+				if age < lifetime {
+					debug!(target: &*request, "== Cache hit for '{}'", key);
+
+					return Self::cached(entry, request, CacheStatus::Hit, now);
+				}
+
+				let stale_limit = control
+					.stale_while_revalidate
+					.map(|swr| lifetime.saturating_add(Duration::from_secs(swr)));
+
+				if !control.must_revalidate && stale_limit.is_some_and(|limit| age < limit) {
+					debug!(target: &*request, "== Serving stale response for '{}'", key);
+
+					return Self::cached(entry, request, CacheStatus::Stale, now);
+				}
+			}
+
+			if let Some(etag) = entry.headers.get(header::ETAG) {
+				if !request.headers.contains_key(header::IF_NONE_MATCH) {
+					request.headers.insert(header::IF_NONE_MATCH, etag.clone())?;
+					conditional.push(header::IF_NONE_MATCH);
+				}
+			}
+
+			if let Some(modified) = entry.headers.get(header::LAST_MODIFIED) {
+				if !request.headers.contains_key(header::IF_MODIFIED_SINCE) {
+					request
+						.headers
+						.insert(header::IF_MODIFIED_SINCE, modified.clone())?;
+					conditional.push(header::IF_MODIFIED_SINCE);
+				}
+			}
+		}
+
+		let request_time = SystemTime::now();
+		let result = transfer(request, None).await;
+
+		for name in &conditional {
+			request.headers.remove(name.clone());
+		}
+
+		let (mut response, reader) = result?;
+		let response_time = SystemTime::now();
+
+		if let Some(entry) = entry.filter(|_| !conditional.is_empty()) {
+			if response.status == StatusCode::NOT_MODIFIED {
+				debug!(target: &*request, "== Revalidated cached response for '{}'", key);
+
+				let mut updated = Entry {
+					vary: entry.vary.clone(),
+					version: entry.version,
+					status: entry.status,
+					headers: entry.headers.clone(),
+					body: entry.body.clone(),
+					request_time,
+					response_time,
+					stored: Instant::now()
+				};
+
+				for (name, value) in &response.headers {
+					if *name != header::CONTENT_LENGTH && *name != header::TRANSFER_ENCODING {
+						updated.headers.insert(name.clone(), value.clone())?;
+					}
+				}
+
+				let updated = match self.store.lock() {
+					Ok(mut store) => store.insert(key, updated),
+					Err(_) => Arc::new(updated)
+				};
+
+				let (mut cached, body) =
+					Self::cached(&updated, request, CacheStatus::Revalidated, response_time)?;
+
+				cached.stats = Stats { cache: Some(CacheStatus::Revalidated), ..response.stats };
+				cached.tls = response.tls;
+				cached.hops = response.hops;
+
+				return Ok((cached, body));
+			}
+		}
+
+		let mut body = Body::new(reader, request, &response)?;
+		let control = CacheControl::parse(&response.headers)?;
+
+		response.stats.cache = Some(CacheStatus::Miss);
+
+		if Self::storable(request, &response, &control)? {
+			let store = self.store.clone();
+			let limit = store.lock().map_or(0, |store| store.capacity);
+
+			if body.remaining().map_or(true, |len| len <= limit as u64) {
+				body.observe(Box::new(Capture {
+					store,
+					key,
+					entry: Some(Self::new_entry(request, &response, request_time, response_time)?),
+					data: Vec::new(),
+					limit
+				}));
+			}
+		}
+
+		Ok((response, body))
+	}
+}
+
+impl Default for Cache {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn allows(status: StatusCode, pairs: &[(HeaderName, &str)]) -> bool {
+		let mut headers = Headers::new();
+
+		for (name, value) in pairs {
+			headers.insert(name.clone(), *value).unwrap();
+		}
+
+		allows_storing(status, &headers, &CacheControl::parse(&headers).unwrap())
+	}
+
+	#[test]
+	fn test_heuristic_statuses() {
+		assert!(allows(StatusCode::OK, &[]));
+		assert!(allows(StatusCode::NOT_FOUND, &[]));
+		assert!(!allows(StatusCode::CREATED, &[]));
+		assert!(!allows(StatusCode::INTERNAL_SERVER_ERROR, &[]));
+	}
+
+	#[test]
+	fn test_validators_not_storable() {
+		let status = StatusCode::INTERNAL_SERVER_ERROR;
+
+		assert!(!allows(status, &[(header::ETAG, "\"abc\"")]));
+		assert!(!allows(status, &[(header::LAST_MODIFIED, "Sun, 06 Nov 1994 08:49:37 GMT")]));
+	}
+
+	#[test]
+	fn test_explicit_freshness() {
+		let status = StatusCode::INTERNAL_SERVER_ERROR;
+
+		assert!(allows(status, &[(header::CACHE_CONTROL, "max-age=60")]));
+		assert!(allows(status, &[(header::CACHE_CONTROL, "public")]));
+		assert!(allows(status, &[(header::CACHE_CONTROL, "private")]));
+		assert!(allows(status, &[(header::EXPIRES, "Sun, 06 Nov 1994 08:49:37 GMT")]));
+		assert!(!allows(status, &[(header::CACHE_CONTROL, "s-maxage=60")]));
+	}
+}
@@ -52,11 +52,67 @@ pub enum HttpError {
 	#[kind = ErrorKind::InvalidData]
 	InvalidHeader(HeaderName, String),
 
+	#[display("Missing header '{}'", f0)]
+	#[kind = ErrorKind::InvalidData]
+	MissingHeader(HeaderName),
+
+	#[display("Header line without a colon: {}", f0)]
+	#[kind = ErrorKind::InvalidData]
+	MissingHeaderSeparator(String),
+
+	#[display("Whitespace between header name and colon: {}", f0)]
+	#[kind = ErrorKind::InvalidData]
+	HeaderNameWhitespace(String),
+
+	#[display("Obsolete line folding in headers")]
+	#[kind = ErrorKind::InvalidData]
+	ObsoleteLineFolding,
+
+	#[display("Line terminated by a bare LF")]
+	#[kind = ErrorKind::InvalidData]
+	BareLineFeed,
+
+	#[display("Both Content-Length and Transfer-Encoding are present")]
+	#[kind = ErrorKind::InvalidData]
+	ConflictingLength,
+
+	#[display("Multiple differing Content-Length headers")]
+	#[kind = ErrorKind::InvalidData]
+	DuplicateContentLength,
+
 	#[display("Chunk too large")]
 	#[kind = ErrorKind::Overflow]
 	ChunkTooLarge,
 
+	#[display("Body too large")]
+	#[kind = ErrorKind::Overflow]
+	BodyTooLarge,
+
+	#[display("Server does not support range requests")]
+	#[kind = ErrorKind::InvalidData]
+	RangeNotSupported,
+
+	#[display("Content-Range does not match the requested range")]
+	#[kind = ErrorKind::InvalidData]
+	RangeMismatch,
+
+	#[display("Unknown charset '{}'", f0)]
+	#[kind = ErrorKind::InvalidData]
+	UnknownCharset(String),
+
+	#[display("Body is not valid {}", f0)]
+	#[kind = ErrorKind::InvalidData]
+	InvalidText(&'static str),
+
+	#[display("Unexpected status {}", f0)]
+	#[kind = ErrorKind::InvalidData]
+	UnexpectedStatus(StatusCode),
+
 	#[display("Unexpected version {}", f0)]
 	#[kind = ErrorKind::InvalidData]
-	UnexpectedVersion(Version)
+	UnexpectedVersion(Version),
+
+	#[display("Server selected unsupported protocol '{}' with ALPN", f0)]
+	#[kind = ErrorKind::InvalidData]
+	UnsupportedProtocol(String)
 }
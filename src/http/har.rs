@@ -0,0 +1,433 @@
+use std::fmt::Write as _;
+use std::mem::take;
+use std::str::from_utf8;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use super::*;
+
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Time taken by each phase of an exchange, in milliseconds. `None` when the
+/// phase did not apply
+#[derive(Default, Clone, Copy)]
+struct Timings {
+	dns: Option<f64>,
+	connect: Option<f64>,
+	ssl: Option<f64>,
+	send: f64,
+	wait: f64,
+	receive: f64
+}
+
+impl Timings {
+	fn from_stats(stats: &Stats) -> Self {
+		let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+		let tls = stats.tls_connect.map(ms);
+
+		Self {
+			dns: stats.connect.map(|connect| ms(connect.dns_resolve)),
+			/* connect includes the time spent on tls */
+			connect: stats
+				.connect
+				.map(|connect| ms(connect.tcp_connect) + tls.unwrap_or_default()),
+			ssl: tls,
+			send: ms(stats.stall),
+			wait: ms(stats.wait),
+			receive: ms(stats.response.saturating_sub(stats.wait))
+		}
+	}
+
+	fn total(&self) -> f64 {
+		self.dns.unwrap_or_default() +
+			self.connect.unwrap_or_default() +
+			self.send + self.wait +
+			self.receive
+	}
+}
+
+struct Content {
+	size: u64,
+	data: Vec<u8>,
+	truncated: bool
+}
+
+struct Entry {
+	started: SystemTime,
+	method: Method,
+	url: Url,
+	request_version: Version,
+	request_headers: Headers,
+	post_data: Option<Vec<u8>>,
+	body_size: Option<u64>,
+	version: Version,
+	status: StatusCode,
+	headers: Headers,
+	redirect: Option<Url>,
+	content: Option<Content>,
+	timings: Timings
+}
+
+/// Records HTTP exchanges and writes them as a HAR 1.2 archive, which can be
+/// imported into browser developer tools. Clones share the same recording
+#[derive(Clone)]
+pub struct HarRecorder {
+	entries: Arc<Mutex<Vec<Entry>>>,
+	max_body_size: usize
+}
+
+/// Captures the response body of a recorded entry
+struct Capture {
+	entries: Arc<Mutex<Vec<Entry>>>,
+	index: usize,
+	content: Content,
+	limit: usize,
+	start: Instant
+}
+
+impl BodyObserver for Capture {
+	fn data(&mut self, data: &[u8]) {
+		self.content.size = self.content.size.saturating_add(data.len() as u64);
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let space = self.limit - self.content.data.len().min(self.limit);
+
+		if data.len() > space {
+			self.content.truncated = true;
+		}
+
+		self.content
+			.data
+			.extend_from_slice(&data[0..data.len().min(space)]);
+	}
+
+	fn complete(&mut self) {
+		let Ok(mut entries) = self.entries.lock() else {
+			return;
+		};
+
+		let Some(entry) = entries.get_mut(self.index) else {
+			return;
+		};
+
+		entry.timings.receive += self.start.elapsed().as_secs_f64() * 1000.0;
+		entry.content = Some(Content {
+			size: self.content.size,
+			data: take(&mut self.content.data),
+			truncated: self.content.truncated
+		});
+	}
+}
+
+fn write_str(out: &mut String, value: &str) {
+	out.push('"');
+
+	for ch in value.chars() {
+		match ch {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			ch if ch.is_control() => {
+				let _ = write!(out, "\\u{:04x}", ch as u32);
+			}
+
+			ch => out.push(ch)
+		}
+	}
+
+	out.push('"');
+}
+
+fn write_headers(out: &mut String, headers: &Headers) {
+	out.push('[');
+
+	for (index, (name, value)) in headers.iter().enumerate() {
+		if index > 0 {
+			out.push(',');
+		}
+
+		out.push_str("{\"name\":");
+		write_str(out, name.as_str());
+		out.push_str(",\"value\":");
+
+		if value.is_sensitive() {
+			write_str(out, "<sensitive>");
+		} else {
+			write_str(out, &String::from_utf8_lossy(value.as_bytes()));
+		}
+
+		out.push('}');
+	}
+
+	out.push(']');
+}
+
+fn write_query(out: &mut String, url: &Url) {
+	out.push('[');
+
+	for (index, (name, value)) in url.query_pairs().enumerate() {
+		if index > 0 {
+			out.push(',');
+		}
+
+		out.push_str("{\"name\":");
+		write_str(out, &name);
+		out.push_str(",\"value\":");
+		write_str(out, &value);
+		out.push('}');
+	}
+
+	out.push(']');
+}
+
+fn write_timing(out: &mut String, name: &str, value: Option<f64>) {
+	let _ = write!(out, ",\"{}\":{:.3}", name, value.unwrap_or(-1.0));
+}
+
+/// Formats `time` as an ISO 8601 date in UTC
+fn format_iso8601(time: SystemTime) -> String {
+	let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+	let secs = since_epoch.as_secs();
+
+	#[allow(clippy::arithmetic_side_effects, clippy::cast_possible_wrap)]
+	let (year, month, day) = {
+		/* days to civil date, from http://howardhinnant.github.io/date_algorithms.html */
+		let days = (secs / 86400) as i64 + 719_468;
+		let era = days.div_euclid(146_097);
+		let doe = days.rem_euclid(146_097);
+		let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+		let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+		let mp = (5 * doy + 2) / 153;
+		let day = doy - (153 * mp + 2) / 5 + 1;
+		let month = if mp < 10 { mp + 3 } else { mp - 9 };
+		let year = yoe + era * 400 + i64::from(month <= 2);
+
+		(year, month, day)
+	};
+
+	#[allow(clippy::arithmetic_side_effects)]
+	let (hour, minute, second) = ((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+		year,
+		month,
+		day,
+		hour,
+		minute,
+		second,
+		since_epoch.subsec_millis()
+	)
+}
+
+fn mime_type(headers: &Headers) -> &str {
+	headers
+		.get_str(header::CONTENT_TYPE)
+		.ok()
+		.flatten()
+		.unwrap_or("")
+}
+
+impl Entry {
+	fn write(&self, out: &mut String) {
+		let timings = &self.timings;
+
+		out.push_str("{\"startedDateTime\":");
+		write_str(out, &format_iso8601(self.started));
+		let _ = write!(out, ",\"time\":{:.3}", timings.total());
+
+		/* request */
+		out.push_str(",\"request\":{\"method\":");
+		write_str(out, self.method.as_str());
+		out.push_str(",\"url\":");
+		write_str(out, self.url.as_str());
+		out.push_str(",\"httpVersion\":");
+		write_str(out, self.request_version.as_str());
+		out.push_str(",\"cookies\":[],\"headers\":");
+		write_headers(out, &self.request_headers);
+		out.push_str(",\"queryString\":");
+		write_query(out, &self.url);
+
+		if let Some(data) = &self.post_data {
+			out.push_str(",\"postData\":{\"mimeType\":");
+			write_str(out, mime_type(&self.request_headers));
+			out.push_str(",\"text\":");
+			write_str(out, &String::from_utf8_lossy(data));
+			out.push('}');
+		}
+
+		let _ = write!(
+			out,
+			",\"headersSize\":-1,\"bodySize\":{}}}",
+			self.body_size.map_or(-1, |size| size.try_into().unwrap_or(i64::MAX))
+		);
+
+		/* response */
+		let _ = write!(out, ",\"response\":{{\"status\":{}", self.status.as_u16());
+		out.push_str(",\"statusText\":");
+		write_str(out, self.status.canonical_reason().unwrap_or(""));
+		out.push_str(",\"httpVersion\":");
+		write_str(out, self.version.as_str());
+		out.push_str(",\"cookies\":[],\"headers\":");
+		write_headers(out, &self.headers);
+
+		let (size, body_size) = match &self.content {
+			Some(content) => (content.size, content.size.try_into().unwrap_or(i64::MAX)),
+			None => (0, -1)
+		};
+
+		let _ = write!(out, ",\"content\":{{\"size\":{}", size);
+		out.push_str(",\"mimeType\":");
+		write_str(out, mime_type(&self.headers));
+
+		if let Some(content) = self.content.as_ref().filter(|content| !content.data.is_empty()) {
+			out.push_str(",\"text\":");
+
+			match from_utf8(&content.data) {
+				Ok(text) => write_str(out, text),
+				Err(_) => {
+					write_str(out, &STANDARD.encode(&content.data));
+					out.push_str(",\"encoding\":\"base64\"");
+				}
+			}
+
+			if content.truncated {
+				out.push_str(",\"comment\":\"truncated\"");
+			}
+		}
+
+		out.push_str("},\"redirectURL\":");
+		write_str(out, self.redirect.as_ref().map_or("", Url::as_str));
+		let _ = write!(out, ",\"headersSize\":-1,\"bodySize\":{}}}", body_size);
+
+		/* timings */
+		out.push_str(",\"cache\":{},\"timings\":{\"blocked\":-1");
+		write_timing(out, "dns", timings.dns);
+		write_timing(out, "connect", timings.connect);
+		write_timing(out, "ssl", timings.ssl);
+		write_timing(out, "send", Some(timings.send));
+		write_timing(out, "wait", Some(timings.wait));
+		write_timing(out, "receive", Some(timings.receive));
+		out.push_str("}}");
+	}
+}
+
+#[asynchronous]
+impl HarRecorder {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::with_max_body_size(DEFAULT_MAX_BODY_SIZE)
+	}
+
+	/// Creates a recorder that keeps at most `size` bytes of each request and
+	/// response body
+	#[must_use]
+	pub fn with_max_body_size(size: usize) -> Self {
+		Self { entries: Arc::new(Mutex::new(Vec::new())), max_body_size: size }
+	}
+
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.entries.lock().map_or(0, |entries| entries.len())
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Records an exchange, returning its index
+	#[allow(clippy::too_many_arguments)]
+	pub(crate) fn record(
+		&self, request: &Request, url: &Url, version: Version, payload: Option<&Payload>,
+		response: &RawResponse, redirect: Option<&Url>, started: SystemTime
+	) -> Option<usize> {
+		let (post_data, body_size) = match payload.map(|payload| &payload.0) {
+			Some(PayloadRepr::Bytes(bytes)) => (
+				Some(bytes[0..bytes.len().min(self.max_body_size)].to_vec()),
+				Some(bytes.len() as u64)
+			),
+
+			Some(PayloadRepr::Stream(_)) => (None, None),
+			None => (None, Some(0))
+		};
+
+		let entry = Entry {
+			started,
+			method: request.method.clone(),
+			url: url.clone(),
+			request_version: version,
+			request_headers: request.headers.clone(),
+			post_data,
+			body_size,
+			version: response.version,
+			status: response.status,
+			headers: response.headers.clone(),
+			redirect: redirect.cloned(),
+			content: None,
+			timings: Timings::from_stats(&response.stats)
+		};
+
+		let mut entries = self.entries.lock().ok()?;
+
+		entries.push(entry);
+
+		#[allow(clippy::arithmetic_side_effects)]
+		Some(entries.len() - 1)
+	}
+
+	/// Captures the response body for the entry at `index`
+	pub(crate) fn capture(&self, body: &mut Body, index: usize) {
+		body.observe(Box::new(Capture {
+			entries: self.entries.clone(),
+			index,
+			content: Content { size: 0, data: Vec::new(), truncated: false },
+			limit: self.max_body_size,
+			start: Instant::now()
+		}));
+	}
+
+	/// The recorded exchanges as HAR 1.2 JSON
+	#[must_use]
+	pub fn to_json(&self) -> String {
+		let mut out = String::new();
+
+		out.push_str("{\"log\":{\"version\":\"1.2\",\"creator\":{\"name\":");
+		write_str(&mut out, env!("CARGO_PKG_NAME"));
+		out.push_str(",\"version\":");
+		write_str(&mut out, env!("CARGO_PKG_VERSION"));
+		out.push_str("},\"entries\":[");
+
+		if let Ok(entries) = self.entries.lock() {
+			for (index, entry) in entries.iter().enumerate() {
+				if index > 0 {
+					out.push(',');
+				}
+
+				entry.write(&mut out);
+			}
+		}
+
+		out.push_str("]}}");
+		out
+	}
+
+	#[allow(clippy::impl_trait_in_params)]
+	pub async fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+		writer.write_all(self.to_json().as_bytes()).await?;
+		writer.flush().await?;
+
+		Ok(())
+	}
+}
+
+impl Default for HarRecorder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
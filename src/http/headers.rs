@@ -0,0 +1,214 @@
+use std::str::FromStr;
+
+use super::*;
+
+/// An entity tag from an `ETag` header
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ETag {
+	tag: String,
+	weak: bool
+}
+
+impl ETag {
+	#[must_use]
+	pub fn strong(tag: &str) -> Self {
+		Self { tag: tag.to_string(), weak: false }
+	}
+
+	#[must_use]
+	pub fn weak(tag: &str) -> Self {
+		Self { tag: tag.to_string(), weak: true }
+	}
+
+	/// The opaque tag, without quotes
+	#[must_use]
+	pub fn tag(&self) -> &str {
+		&self.tag
+	}
+
+	#[must_use]
+	pub const fn is_weak(&self) -> bool {
+		self.weak
+	}
+
+	/// Strong comparison, as used by `If-Match` and `If-Range`
+	#[must_use]
+	pub fn strong_eq(&self, other: &Self) -> bool {
+		!self.weak && !other.weak && self.tag == other.tag
+	}
+
+	/// Weak comparison, as used by `If-None-Match`
+	#[must_use]
+	pub fn weak_eq(&self, other: &Self) -> bool {
+		self.tag == other.tag
+	}
+
+	fn parse(value: &str) -> Option<Self> {
+		let value = value.trim();
+		let (weak, value) = match value.strip_prefix("W/") {
+			Some(value) => (true, value),
+			None => (false, value)
+		};
+
+		let tag = value.strip_prefix('"')?.strip_suffix('"')?;
+
+		if tag.contains('"') {
+			return None;
+		}
+
+		Some(Self { tag: tag.to_string(), weak })
+	}
+}
+
+impl FromStr for ETag {
+	type Err = Error;
+
+	fn from_str(value: &str) -> Result<Self> {
+		Self::parse(value)
+			.ok_or_else(|| HttpError::InvalidHeader(header::ETAG, value.into()).into())
+	}
+}
+
+impl fmt::Display for ETag {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.weak {
+			fmt.write_str("W/")?;
+		}
+
+		write!(fmt, "\"{}\"", self.tag)
+	}
+}
+
+/// A parsed `Content-Type` header value
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MediaType {
+	value: String,
+	essence: String
+}
+
+impl MediaType {
+	fn parse(value: &str) -> Option<Self> {
+		let essence = value.split(';').next().unwrap_or_default().trim();
+		let (kind, subtype) = essence.split_once('/')?;
+
+		let is_token = |str: &str| {
+			!str.is_empty() &&
+				str.bytes()
+					.all(|byte| byte.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&byte))
+		};
+
+		if !is_token(kind) || !is_token(subtype) {
+			return None;
+		}
+
+		Some(Self {
+			value: value.trim().to_string(),
+			essence: essence.to_ascii_lowercase()
+		})
+	}
+
+	/// The lowercase `type/subtype`, without parameters
+	#[must_use]
+	pub fn essence(&self) -> &str {
+		&self.essence
+	}
+
+	/// The lowercase top level type, such as `text`
+	#[must_use]
+	pub fn kind(&self) -> &str {
+		self.essence.split_once('/').map_or("", |(kind, _)| kind)
+	}
+
+	/// The lowercase subtype, such as `html`
+	#[must_use]
+	pub fn subtype(&self) -> &str {
+		self.essence.split_once('/').map_or("", |(_, subtype)| subtype)
+	}
+
+	/// The value of the parameter `name`, with any quoting removed
+	#[must_use]
+	pub fn param(&self, name: &str) -> Option<String> {
+		header_parameter(&self.value, name)
+	}
+
+	#[must_use]
+	pub fn charset(&self) -> Option<String> {
+		self.param("charset")
+	}
+}
+
+impl FromStr for MediaType {
+	type Err = Error;
+
+	fn from_str(value: &str) -> Result<Self> {
+		Self::parse(value)
+			.ok_or_else(|| HttpError::InvalidHeader(header::CONTENT_TYPE, value.into()).into())
+	}
+}
+
+impl fmt::Display for MediaType {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt.write_str(&self.value)
+	}
+}
+
+pub(crate) fn format_date(time: SystemTime) -> String {
+	httpdate::fmt_http_date(time)
+}
+
+impl Headers {
+	/// Parses the header `key` as an HTTP date
+	pub fn get_date(&self, key: HeaderName) -> Result<Option<SystemTime>> {
+		let Some(value) = self.get_str(key.clone())? else {
+			return Ok(None);
+		};
+
+		match httpdate::parse_http_date(value) {
+			Ok(time) => Ok(Some(time)),
+			Err(_) => Err(HttpError::InvalidHeader(key, value.to_string()).into())
+		}
+	}
+
+	/// Parses the header `key` with `T`'s [`FromStr`] implementation
+	pub fn get_parsed<T: FromStr>(&self, key: HeaderName) -> Result<Option<T>> {
+		let Some(value) = self.get_str(key.clone())? else {
+			return Ok(None);
+		};
+
+		match value.trim().parse() {
+			Ok(value) => Ok(Some(value)),
+			Err(_) => Err(HttpError::InvalidHeader(key, value.to_string()).into())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_etag() {
+		let strong: ETag = "\"abc\"".parse().unwrap();
+		let weak: ETag = " W/\"abc\" ".parse().unwrap();
+
+		assert_eq!(strong, ETag::strong("abc"));
+		assert_eq!(weak, ETag::weak("abc"));
+		assert_eq!(weak.to_string(), "W/\"abc\"");
+		assert_eq!("\"\"".parse::<ETag>().unwrap().tag(), "");
+
+		assert!("abc".parse::<ETag>().is_err());
+		assert!("\"a\"b\"".parse::<ETag>().is_err());
+		assert!("w/\"abc\"".parse::<ETag>().is_err());
+	}
+
+	#[test]
+	fn test_etag_comparison() {
+		let strong = ETag::strong("abc");
+		let weak = ETag::weak("abc");
+
+		assert!(strong.strong_eq(&ETag::strong("abc")));
+		assert!(!strong.strong_eq(&weak));
+		assert!(strong.weak_eq(&weak));
+		assert!(!strong.weak_eq(&ETag::strong("abd")));
+	}
+}
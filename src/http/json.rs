@@ -0,0 +1,109 @@
+use std::io;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use xx_core::coroutines::{get_context, scoped, Context};
+
+use super::*;
+
+/// The default maximum size of a JSON response body
+pub const DEFAULT_MAX_JSON_SIZE: u64 = 16 * 1024 * 1024;
+
+fn json_error(err: serde_json::Error) -> Error {
+	if err.is_io() {
+		io::Error::from(err).into()
+	} else {
+		Error::new(err)
+	}
+}
+
+/// Reads the body synchronously for `serde_json`, failing once more than
+/// `remaining` bytes have been read
+struct Adapter<'a> {
+	body: &'a mut Body,
+	context: &'a Context,
+	remaining: u64
+}
+
+#[allow(unsafe_code)]
+impl<'a> Adapter<'a> {
+	/// # Safety
+	/// Calls to io functions must be allowed to suspend
+	unsafe fn new(body: &'a mut Body, context: &'a Context, remaining: u64) -> Self {
+		Self { body, context, remaining }
+	}
+}
+
+#[allow(unsafe_code)]
+impl io::Read for Adapter<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		/* allow reading one byte past the limit to detect oversized bodies */
+		let limit = self.remaining.saturating_add(1);
+		let len = buf.len().min(limit.try_into().unwrap_or(usize::MAX));
+
+		/* Safety: guaranteed by caller */
+		let read = unsafe { scoped(self.context, self.body.read(&mut buf[0..len])) }?;
+
+		if read as u64 > self.remaining {
+			return Err(Error::from(HttpError::BodyTooLarge).into());
+		}
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(self.remaining -= read as u64);
+
+		Ok(read)
+	}
+}
+
+impl Request {
+	/// Serializes `value` as the request body, setting `Content-Type` to
+	/// `application/json`
+	pub fn json<T: Serialize + ?Sized>(&mut self, value: &T) -> &mut Self {
+		match serde_json::to_vec(value) {
+			Ok(json) => {
+				self.body = Some(json.into());
+				self.header(header::CONTENT_TYPE, "application/json")
+			}
+
+			Err(err) => {
+				self.request.fail(json_error(err));
+				self
+			}
+		}
+	}
+}
+
+impl HttpRequest {
+	pub fn json<T: Serialize + ?Sized>(&mut self, value: &T) -> &mut Self {
+		self.inner.json(value);
+		self
+	}
+}
+
+#[asynchronous]
+impl Response {
+	/// Deserializes the body as JSON, reading at most
+	/// [`DEFAULT_MAX_JSON_SIZE`] bytes
+	pub async fn json<T: DeserializeOwned>(&mut self) -> Result<T> {
+		self.json_limited(DEFAULT_MAX_JSON_SIZE).await
+	}
+
+	/// Deserializes the body as JSON, failing if it is larger than `max_size`
+	/// bytes
+	pub async fn json_limited<T: DeserializeOwned>(&mut self, max_size: u64) -> Result<T> {
+		if self.content_length()?.is_some_and(|len| len > max_size) {
+			return Err(HttpError::BodyTooLarge.into());
+		}
+
+		let context = get_context().await;
+
+		/* Safety: we are in an async function */
+		#[allow(unsafe_code)]
+		let mut adapter = unsafe { Adapter::new(self.body(), context, max_size) };
+		let value = serde_json::from_reader(&mut adapter).map_err(json_error)?;
+
+		check_interrupt().await?;
+
+		Ok(value)
+	}
+}
@@ -1,5 +1,5 @@
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use ::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use ::http::Method;
@@ -14,22 +14,42 @@ use xx_core::{debug, trace, warn};
 
 use super::*;
 
+pub mod auth;
 pub mod body;
+pub mod cache;
 pub mod error;
+pub mod har;
+pub mod headers;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod multipart;
+pub mod range;
+pub(crate) mod rate;
 pub mod request;
 pub mod response;
+pub mod seekable;
 pub mod stats;
 pub(crate) mod stream;
 pub(crate) mod transfer;
 
 pub use ::http::StatusCode;
+pub use auth::*;
 pub use body::*;
+pub use cache::*;
 pub use error::*;
+pub use har::*;
+pub use headers::*;
+#[cfg(feature = "serde")]
+pub use json::*;
+pub use multipart::*;
+pub use range::*;
 pub use request::*;
 pub use response::*;
+pub use seekable::*;
 pub use stats::*;
 use xx_core::macros::strings;
 
+use self::rate::*;
 use self::stream::*;
 use self::transfer::*;
 
@@ -52,6 +72,22 @@ pub enum Version {
 	Http30 = 30
 }
 
+/// How strictly HTTP messages are parsed
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParseMode {
+	/// Reject malformed or ambiguous messages, such as conflicting
+	/// `Content-Length` and `Transfer-Encoding` headers, which could
+	/// otherwise be used for request smuggling
+	#[default]
+	Strict,
+
+	/// Accept headers without a colon, whitespace before the colon, obsolete
+	/// line folding, bare LF line endings, invalid status lines (treated as
+	/// HTTP/0.9), and `Transfer-Encoding` overriding `Content-Length`. Only
+	/// use this for talking to broken legacy servers
+	Lenient
+}
+
 impl fmt::Display for Version {
 	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
 		self.as_str().fmt(fmt)
@@ -203,6 +239,14 @@ enum PayloadRepr {
 
 pub struct Payload(PayloadRepr);
 
+impl Payload {
+	/// Whether the payload can be sent again, streams are consumed by the
+	/// first attempt
+	pub(crate) const fn is_rewindable(&self) -> bool {
+		matches!(self.0, PayloadRepr::Bytes(_))
+	}
+}
+
 impl From<&[u8]> for Payload {
 	fn from(value: &[u8]) -> Self {
 		Self(PayloadRepr::Bytes(value.into()))
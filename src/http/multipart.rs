@@ -0,0 +1,325 @@
+use xx_core::io::read_into_slice;
+use xx_core::pointer::*;
+
+use super::*;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+	/// Before the first delimiter
+	Preamble,
+
+	/// Inside of a part's body
+	Part,
+
+	/// Directly after a delimiter
+	Boundary,
+
+	/// After the close delimiter
+	Finished
+}
+
+/// Returns the value of the parameter `name` in a header value such as
+/// `multipart/mixed; boundary="abc"`
+pub(crate) fn header_parameter(value: &str, name: &str) -> Option<String> {
+	let mut rest = value.split_once(';')?.1;
+
+	loop {
+		let (key, value) = rest.split_once('=')?;
+		let key = key.trim();
+		let value = value.trim_start();
+
+		let (value, next) = if let Some(quoted) = value.strip_prefix('"') {
+			let mut result = String::new();
+			let mut chars = quoted.char_indices();
+
+			let end = loop {
+				match chars.next()? {
+					(_, '\\') => result.push(chars.next()?.1),
+					(index, '"') => break index,
+					(_, ch) => result.push(ch)
+				}
+			};
+
+			#[allow(clippy::arithmetic_side_effects)]
+			let next = quoted[end + 1..].split_once(';').map(|(_, next)| next);
+
+			(result, next)
+		} else {
+			match value.split_once(';') {
+				Some((value, next)) => (value.trim_end().to_string(), Some(next)),
+				None => (value.trim_end().to_string(), None)
+			}
+		};
+
+		if key.eq_ignore_ascii_case(name) {
+			return Some(value);
+		}
+
+		rest = next?;
+	}
+}
+
+/// Find `delimiter` in `buffer`, returning the number of bytes that can
+/// safely be consumed if it isn't found
+fn find_delimiter(buffer: &[u8], delimiter: &[u8]) -> std::result::Result<usize, usize> {
+	let mut offset = 0;
+
+	while let Some(index) = memchr(b'\r', &buffer[offset..]) {
+		#[allow(clippy::arithmetic_side_effects)]
+		let start = offset + index;
+		let candidate = &buffer[start..];
+
+		if candidate.len() < delimiter.len() {
+			if delimiter.starts_with(candidate) {
+				return Err(start);
+			}
+		} else if candidate.starts_with(delimiter) {
+			return Ok(start);
+		}
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(offset = start + 1);
+	}
+
+	Err(buffer.len())
+}
+
+/// A streaming parser for `multipart/*` bodies
+pub struct Multipart {
+	reader: BufReader<Body>,
+	delimiter: Box<[u8]>,
+	mode: ParseMode,
+	state: State
+}
+
+#[asynchronous]
+impl Multipart {
+	#[must_use]
+	pub fn new(body: Body, boundary: &str) -> Self {
+		let delimiter = format!("\r\n--{}", boundary);
+
+		Self {
+			mode: body.parse_mode(),
+			reader: BufReader::new(body),
+			delimiter: delimiter.into_bytes().into_boxed_slice(),
+			state: State::Preamble
+		}
+	}
+
+	pub fn from_response(response: Response) -> Result<Self> {
+		let content_type = response
+			.headers()
+			.get_str(header::CONTENT_TYPE)?
+			.ok_or(HttpError::MissingHeader(header::CONTENT_TYPE))?;
+
+		let is_multipart = content_type
+			.get(0.."multipart/".len())
+			.is_some_and(|prefix| prefix.eq_ignore_ascii_case("multipart/"));
+
+		let boundary = header_parameter(content_type, "boundary")
+			.filter(|boundary| is_multipart && (1..=70).contains(&boundary.len()))
+			.ok_or_else(|| {
+				HttpError::InvalidHeader(header::CONTENT_TYPE, content_type.to_string())
+			})?;
+
+		Ok(Self::new(response.into_body(), &boundary))
+	}
+
+	fn find_delimiter(&self) -> std::result::Result<usize, usize> {
+		find_delimiter(self.reader.buffer(), &self.delimiter)
+	}
+
+	async fn fill(&mut self) -> Result<()> {
+		/* fill does not discard unconsumed bytes */
+		if unlikely(self.reader.fill().await? == 0) {
+			return Err(UrlError::PartialFile.into());
+		}
+
+		Ok(())
+	}
+
+	async fn skip_preamble(&mut self) -> Result<()> {
+		/* the first delimiter isn't required to be preceded by a CRLF */
+		let first = &self.delimiter[2..];
+
+		while self.reader.buffer().len() < first.len() {
+			self.fill().await?;
+		}
+
+		if self.reader.buffer().starts_with(first) {
+			self.reader.consume(first.len());
+			self.state = State::Boundary;
+
+			return Ok(());
+		}
+
+		loop {
+			match self.find_delimiter() {
+				Ok(index) => {
+					#[allow(clippy::arithmetic_side_effects)]
+					self.reader.consume(index + self.delimiter.len());
+					self.state = State::Boundary;
+
+					break Ok(());
+				}
+
+				Err(safe) => {
+					self.reader.consume(safe);
+					self.fill().await?;
+				}
+			}
+		}
+	}
+
+	async fn read_part(&mut self, buf: &mut [u8]) -> Result<usize> {
+		if self.state != State::Part {
+			return Ok(0);
+		}
+
+		loop {
+			let available = match self.find_delimiter() {
+				Ok(0) => {
+					self.reader.consume(self.delimiter.len());
+					self.state = State::Boundary;
+
+					break Ok(0);
+				}
+
+				Ok(index) => index,
+				Err(safe) => safe
+			};
+
+			if available == 0 {
+				self.fill().await?;
+
+				continue;
+			}
+
+			let read = read_into_slice(buf, &self.reader.buffer()[0..available]);
+
+			self.reader.consume(read);
+
+			break Ok(read);
+		}
+	}
+
+	async fn read_boundary(&mut self) -> Result<Option<Headers>> {
+		while self.reader.buffer().len() < 2 {
+			self.fill().await?;
+		}
+
+		if self.reader.buffer().starts_with(b"--") {
+			self.state = State::Finished;
+
+			return Ok(None);
+		}
+
+		/* skip any transport padding after the delimiter */
+		let (_, offset) = read_line_in_place(&mut self.reader).await?;
+
+		self.reader.consume(offset);
+
+		let log = ptr!(&*self);
+		let mut headers = Headers::new();
+
+		read_headers_limited(
+			&mut self.reader,
+			&mut headers,
+			DEFAULT_MAXIMUM_HEADER_SIZE as usize,
+			self.mode,
+			&log
+		)
+		.await?;
+
+		self.state = State::Part;
+
+		Ok(Some(headers))
+	}
+
+	/// Returns the next part, discarding any unread data of the previous
+	/// part. Returns `None` once the close delimiter is reached
+	pub async fn next_part(&mut self) -> Result<Option<Part<'_>>> {
+		loop {
+			match self.state {
+				State::Preamble => self.skip_preamble().await?,
+				State::Part => {
+					let mut discard = [0u8; 256];
+
+					while self.read_part(&mut discard).await? != 0 {}
+				}
+
+				State::Boundary => {
+					let Some(headers) = self.read_boundary().await? else {
+						continue;
+					};
+
+					break Ok(Some(Part { multipart: self, headers }));
+				}
+
+				State::Finished => break Ok(None)
+			}
+
+			check_interrupt().await?;
+		}
+	}
+}
+
+/// A single part of a multipart body. The part's body is read using [`Read`]
+pub struct Part<'a> {
+	multipart: &'a mut Multipart,
+	headers: Headers
+}
+
+impl Part<'_> {
+	#[must_use]
+	pub const fn headers(&self) -> &Headers {
+		&self.headers
+	}
+
+	/// The range of a part in a `multipart/byteranges` body
+	pub fn content_range(&self) -> Result<Option<ContentRange>> {
+		ContentRange::from_headers(&self.headers)
+	}
+}
+
+#[asynchronous]
+impl Read for Part<'_> {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		read_into!(buf);
+
+		self.multipart.read_part(buf).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DELIMITER: &[u8] = b"\r\n--boundary";
+
+	#[test]
+	fn test_find_delimiter() {
+		assert_eq!(find_delimiter(b"data\r\n--boundary\r\n", DELIMITER), Ok(4));
+		assert_eq!(find_delimiter(b"a\rb\r\n--boundary", DELIMITER), Ok(3));
+		assert_eq!(find_delimiter(b"no delimiter", DELIMITER), Err(12));
+		assert_eq!(find_delimiter(b"\r\n--bound", DELIMITER), Err(0));
+	}
+
+	#[test]
+	fn test_find_split_delimiter() {
+		/* the first \r is data, the partial delimiter after it must be kept */
+		assert_eq!(find_delimiter(b"data\r\r\n--b", DELIMITER), Err(5));
+		assert_eq!(find_delimiter(b"data\r", DELIMITER), Err(4));
+		assert_eq!(find_delimiter(b"data\rx", DELIMITER), Err(6));
+	}
+
+	#[test]
+	fn test_header_parameter() {
+		let value = "multipart/mixed; charset=utf-8; boundary=\"a \\\"b\\\" c\"";
+
+		assert_eq!(header_parameter(value, "boundary").as_deref(), Some("a \"b\" c"));
+		assert_eq!(header_parameter(value, "charset").as_deref(), Some("utf-8"));
+		assert_eq!(header_parameter(value, "missing"), None);
+		assert_eq!(header_parameter("multipart/mixed", "boundary"), None);
+	}
+}
@@ -0,0 +1,318 @@
+use super::*;
+
+const DEFAULT_MAX_RESUMES: u32 = 5;
+
+/// A parsed `Content-Range` header. `end` is exclusive
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ContentRange {
+	pub start: u64,
+	pub end: u64,
+	pub total: Option<u64>
+}
+
+impl ContentRange {
+	fn parse(value: &str) -> Option<Self> {
+		let (unit, range) = value.trim().split_once(' ')?;
+
+		if !unit.eq_ignore_ascii_case("bytes") {
+			return None;
+		}
+
+		let (range, total) = range.trim_start().split_once('/')?;
+		let total = match total {
+			"*" => None,
+			total => Some(total.parse().ok()?)
+		};
+
+		let (start, end): (u64, u64) = {
+			let (start, end) = range.split_once('-')?;
+
+			(start.parse().ok()?, end.parse().ok()?)
+		};
+
+		if end < start || total.is_some_and(|total| end >= total) {
+			return None;
+		}
+
+		Some(Self { start, end: end.checked_add(1)?, total })
+	}
+
+	/// The resource length from the `bytes */length` form of `Content-Range`
+	/// sent with a `416 Range Not Satisfiable` response
+	pub(crate) fn unsatisfied_total(headers: &Headers) -> Result<Option<u64>> {
+		let Some(value) = headers.get_str(header::CONTENT_RANGE)? else {
+			return Ok(None);
+		};
+
+		Ok(value
+			.trim()
+			.split_once(' ')
+			.filter(|(unit, _)| unit.eq_ignore_ascii_case("bytes"))
+			.and_then(|(_, range)| range.trim_start().strip_prefix("*/"))
+			.and_then(|total| total.parse().ok()))
+	}
+
+	pub fn from_headers(headers: &Headers) -> Result<Option<Self>> {
+		let Some(value) = headers.get_str(header::CONTENT_RANGE)? else {
+			return Ok(None);
+		};
+
+		match Self::parse(value) {
+			Some(range) => Ok(Some(range)),
+			None => Err(HttpError::InvalidHeader(header::CONTENT_RANGE, value.to_string()).into())
+		}
+	}
+
+	#[must_use]
+	#[allow(clippy::arithmetic_side_effects)]
+	pub const fn len(&self) -> u64 {
+		self.end - self.start
+	}
+
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+/// The value of the `Range` header for the requested range, if any
+pub(crate) fn range_header(options: &Options) -> Result<Option<String>> {
+	let start = options.start.unwrap_or(0);
+
+	Ok(match options.end {
+		None if start == 0 => None,
+		None => Some(format!("bytes={}-", start)),
+		Some(end) if end > start => {
+			#[allow(clippy::arithmetic_side_effects)]
+			Some(format!("bytes={}-{}", start, end - 1))
+		}
+
+		Some(_) => return Err(fmt_error!("Empty range requested" @ ErrorKind::InvalidInput))
+	})
+}
+
+/// The value for an `If-Range` header that ensures the resource hasn't
+/// changed between requests
+pub(crate) fn range_validator(headers: &Headers) -> Option<HeaderValue> {
+	/* weak entity tags cannot be used with If-Range */
+	match headers.get(header::ETAG) {
+		Some(etag) if !etag.as_bytes().starts_with(b"W/") => Some(etag.clone()),
+		_ => headers.get(header::LAST_MODIFIED).cloned()
+	}
+}
+
+/// Checks that the server honored the requested range
+pub(crate) fn check_range(request: &Request, response: &RawResponse) -> Result<()> {
+	if range_header(&request.options)?.is_none() {
+		return Ok(());
+	}
+
+	match response.status {
+		StatusCode::PARTIAL_CONTENT => (),
+		StatusCode::OK => return Err(HttpError::RangeNotSupported.into()),
+		_ => return Ok(())
+	}
+
+	let range = ContentRange::from_headers(&response.headers)?
+		.ok_or(HttpError::MissingHeader(header::CONTENT_RANGE))?;
+
+	let start = request.options.start.unwrap_or(0);
+	let end = request.options.end.unwrap_or(u64::MAX);
+
+	if range.start != start || range.end > end {
+		return Err(HttpError::RangeMismatch.into());
+	}
+
+	Ok(())
+}
+
+/// A download that transparently reconnects and continues from the last
+/// byte received when the connection drops in the middle of the body
+pub struct Download {
+	request: Request,
+	body: Body,
+	position: u64,
+	total: Option<u64>,
+	validator: Option<HeaderValue>,
+	resumes_remaining: u32
+}
+
+#[asynchronous]
+impl Download {
+	pub(super) async fn new(mut request: Request) -> Result<Self> {
+		let (response, reader) = transfer(&mut request, None).await?;
+
+		check_range(&request, &response)?;
+
+		let body = Body::new(reader, &request, &response)?;
+		let position = request.options.start.unwrap_or(0);
+
+		/* the full length of the resource, to detect it changing between requests */
+		let total = if response.status == StatusCode::PARTIAL_CONTENT {
+			ContentRange::from_headers(&response.headers)?.and_then(|range| range.total)
+		} else {
+			body.remaining().map(|remaining| remaining.saturating_add(position))
+		};
+
+		let validator = range_validator(&response.headers);
+
+		if validator.is_none() {
+			debug!(target: &request, "== No validator in response, download cannot be resumed");
+		}
+
+		Ok(Self {
+			request,
+			body,
+			position,
+			total,
+			validator,
+			resumes_remaining: DEFAULT_MAX_RESUMES
+		})
+	}
+
+	pub fn set_max_resumes(&mut self, resumes: u32) -> &mut Self {
+		self.resumes_remaining = resumes;
+		self
+	}
+
+	/// The offset of the next byte to be read
+	#[must_use]
+	pub const fn position(&self) -> u64 {
+		self.position
+	}
+
+	#[must_use]
+	pub const fn remaining(&self) -> Option<u64> {
+		self.body.remaining()
+	}
+
+	async fn resume(&mut self) -> Result<()> {
+		let Some(validator) = self.validator.clone() else {
+			return Err(UrlError::PartialFile.into());
+		};
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(self.resumes_remaining -= 1);
+
+		debug!(target: &*self, "== Resuming download at byte {}", self.position);
+
+		self.request.options.start = Some(self.position);
+		self.request.headers.insert(header::IF_RANGE, validator)?;
+
+		let (response, reader) = transfer(&mut self.request, None).await?;
+
+		if response.status != StatusCode::PARTIAL_CONTENT {
+			return Err(UrlError::PartialFile.into());
+		}
+
+		check_range(&self.request, &response)?;
+
+		let range = ContentRange::from_headers(&response.headers)?;
+
+		if let (Some(total), Some(range)) = (self.total, range) {
+			if range.total != Some(total) {
+				debug!(
+					target: &*self,
+					"== Resource length changed from {} to {:?}",
+					total,
+					range.total
+				);
+
+				return Err(HttpError::RangeMismatch.into());
+			}
+		}
+
+		/* bytes already read count towards the size limit */
+		let max_size = self.body.max_size_remaining();
+
+		self.body = Body::with_max_size(reader, &self.request, &response, max_size)?;
+
+		Ok(())
+	}
+}
+
+#[asynchronous]
+impl Read for Download {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		read_into!(buf);
+
+		loop {
+			match self.body.read(buf).await {
+				Ok(read) => {
+					#[allow(clippy::arithmetic_side_effects)]
+					(self.position += read as u64);
+
+					break Ok(read);
+				}
+
+				Err(err)
+					if err.kind() == ErrorKind::UnexpectedEof && self.resumes_remaining > 0 =>
+				{
+					debug!(target: &*self, "== Download interrupted: {:?}", err);
+
+					self.resume().await?;
+				}
+
+				Err(err) => break Err(err)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_content_range() {
+		assert_eq!(
+			ContentRange::parse("bytes 0-499/1234"),
+			Some(ContentRange { start: 0, end: 500, total: Some(1234) })
+		);
+
+		assert_eq!(
+			ContentRange::parse("BYTES 500-999/*"),
+			Some(ContentRange { start: 500, end: 1000, total: None })
+		);
+
+		assert_eq!(ContentRange::parse("bytes 5-4/10"), None);
+		assert_eq!(ContentRange::parse("bytes 0-10/10"), None);
+		assert_eq!(ContentRange::parse("bytes */10"), None);
+		assert_eq!(ContentRange::parse("items 0-1/2"), None);
+		assert_eq!(ContentRange::parse("bytes 0-/10"), None);
+	}
+
+	#[test]
+	fn test_unsatisfied_total() {
+		let mut headers = Headers::new();
+
+		assert_eq!(ContentRange::unsatisfied_total(&headers).unwrap(), None);
+
+		headers.insert(header::CONTENT_RANGE, "bytes */0").unwrap();
+
+		assert_eq!(ContentRange::unsatisfied_total(&headers).unwrap(), Some(0));
+
+		headers.insert(header::CONTENT_RANGE, "bytes 0-1/2").unwrap();
+
+		assert_eq!(ContentRange::unsatisfied_total(&headers).unwrap(), None);
+	}
+
+	#[test]
+	fn test_range_header() {
+		let mut options = Options::new();
+
+		assert_eq!(range_header(&options).unwrap(), None);
+
+		options.start = Some(100);
+
+		assert_eq!(range_header(&options).unwrap().as_deref(), Some("bytes=100-"));
+
+		options.end = Some(200);
+
+		assert_eq!(range_header(&options).unwrap().as_deref(), Some("bytes=100-199"));
+
+		options.end = Some(100);
+
+		assert!(range_header(&options).is_err());
+	}
+}
@@ -0,0 +1,79 @@
+use super::*;
+
+/* the smallest fraction of a second's worth of bytes to wait for, so that
+ * slow rates don't result in many tiny reads and writes */
+const MIN_BURST_DIVISOR: u64 = 20;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A token bucket allowing `rate` bytes per second, with a burst of up to one
+/// second's worth of bytes
+pub(crate) struct RateLimiter {
+	rate: u64,
+	tokens: u64,
+	last: Instant
+}
+
+#[asynchronous]
+impl RateLimiter {
+	pub(crate) fn new(rate: u64) -> Self {
+		let rate = rate.max(1);
+
+		Self { rate, tokens: rate, last: Instant::now() }
+	}
+
+	fn refill(&mut self) {
+		let now = Instant::now();
+		let elapsed = now.saturating_duration_since(self.last).as_nanos();
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let added = elapsed.saturating_mul(self.rate.into()) / NANOS_PER_SEC;
+
+		/* keep the remainder for the next refill */
+		if added == 0 {
+			return;
+		}
+
+		self.tokens = self
+			.tokens
+			.saturating_add(added.try_into().unwrap_or(u64::MAX))
+			.min(self.rate);
+		self.last = now;
+	}
+
+	/// Waits until some bytes may be transferred, returning how many up to
+	/// `amount`
+	pub(crate) async fn acquire(&mut self, amount: usize) -> Result<usize> {
+		let amount = amount.try_into().unwrap_or(u64::MAX);
+
+		#[allow(clippy::arithmetic_side_effects)]
+		let want = amount.min((self.rate / MIN_BURST_DIVISOR).max(1));
+
+		loop {
+			self.refill();
+
+			if self.tokens >= want {
+				let acquired = self.tokens.min(amount);
+
+				#[allow(clippy::arithmetic_side_effects)]
+				(self.tokens -= acquired);
+
+				#[allow(clippy::cast_possible_truncation)]
+				break Ok(acquired as usize);
+			}
+
+			#[allow(clippy::arithmetic_side_effects)]
+			let nanos = u128::from(want - self.tokens) * NANOS_PER_SEC / u128::from(self.rate);
+
+			sleep(Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX))).await?;
+		}
+	}
+
+	/// Returns tokens that were acquired but not used
+	pub(crate) fn release(&mut self, amount: usize) {
+		self.tokens = self
+			.tokens
+			.saturating_add(amount.try_into().unwrap_or(u64::MAX))
+			.min(self.rate);
+	}
+}
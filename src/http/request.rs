@@ -1,3 +1,6 @@
+use std::sync::Arc;
+
+use rustls::ClientConfig;
 use xx_core::coroutines::Task;
 use xx_core::macros::wrapper_functions;
 
@@ -29,11 +32,78 @@ impl HttpRequest {
 
 		#[allow(clippy::impl_trait_in_params)]
 		pub fn payload(&mut self, payload: impl Into<Payload>) -> &mut Self;
+
+		pub fn start(&mut self, start: u64) -> &mut Self;
+
+		pub fn end(&mut self, end: u64) -> &mut Self;
+
+		pub fn set_parse_mode(&mut self, mode: ParseMode) -> &mut Self;
+
+		pub fn set_max_body_size(&mut self, size: u64) -> &mut Self;
+
+		pub fn set_download_rate(&mut self, rate: u64) -> &mut Self;
+
+		pub fn set_upload_rate(&mut self, rate: u64) -> &mut Self;
+
+		#[allow(clippy::impl_trait_in_params)]
+		pub fn on_upload_progress(
+			&mut self, callback: impl Fn(&Progress) + Send + Sync + 'static
+		) -> &mut Self;
+
+		#[allow(clippy::impl_trait_in_params)]
+		pub fn on_download_progress(
+			&mut self, callback: impl Fn(&Progress) + Send + Sync + 'static
+		) -> &mut Self;
+
+		#[allow(clippy::impl_trait_in_params)]
+		pub fn on_event(
+			&mut self, callback: impl Fn(&Event<'_>) + Send + Sync + 'static
+		) -> &mut Self;
+
+		pub fn set_har_recorder(&mut self, recorder: HarRecorder) -> &mut Self;
+
+		pub fn basic_auth(&mut self, username: &str, password: &str) -> &mut Self;
+
+		pub fn bearer_auth(&mut self, token: &str) -> &mut Self;
+
+		pub fn digest_auth(&mut self, username: &str, password: &str) -> &mut Self;
+
+		pub fn if_match(&mut self, etag: &ETag) -> &mut Self;
+
+		pub fn if_none_match(&mut self, etag: &ETag) -> &mut Self;
+
+		pub fn if_modified_since(&mut self, time: SystemTime) -> &mut Self;
+
+		pub fn if_unmodified_since(&mut self, time: SystemTime) -> &mut Self;
+
+		pub fn set_cache(&mut self, cache: Cache) -> &mut Self;
+
+		pub fn set_tls_config(&mut self, config: Arc<ClientConfig>) -> &mut Self;
+
+		pub fn danger_accept_invalid_certs(&mut self, accept: bool) -> &mut Self;
+
+		pub fn danger_accept_invalid_hostnames(&mut self, accept: bool) -> &mut Self;
+
+		pub fn set_early_data(&mut self, enable: bool) -> &mut Self;
+
+		pub fn allow_tls_truncation(&mut self, allow: bool) -> &mut Self;
 	}
 
 	pub async fn run(&mut self) -> Result<Response> {
 		Response::fetch(self).await
 	}
+
+	/// Start a download that resumes from the last byte received if the
+	/// connection is interrupted
+	pub async fn download(self) -> Result<Download> {
+		Download::new(self.inner).await
+	}
+
+	/// Open a body that can be seeked using range requests. The server must
+	/// support byte ranges
+	pub async fn seekable(self) -> Result<SeekableBody> {
+		SeekableBody::new(self.inner).await
+	}
 }
 
 #[asynchronous(task)]
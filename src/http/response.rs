@@ -1,4 +1,7 @@
+use encoding_rs::{Encoding, UTF_8};
+
 use super::*;
+use crate::tls::conn::TlsInfo;
 
 pub struct Response {
 	response: RawResponse,
@@ -8,7 +11,16 @@ pub struct Response {
 #[asynchronous]
 impl Response {
 	pub async fn fetch(request: &mut HttpRequest) -> Result<Self> {
+		if let Some(cache) = request.inner.cache.clone() {
+			let (response, body) = cache.fetch(&mut request.inner).await?;
+
+			return Ok(Self { response, body });
+		}
+
 		let (response, reader) = transfer(&mut request.inner, None).await?;
+
+		check_range(&request.inner, &response)?;
+
 		let body = Body::new(reader, &request.inner, &response)?;
 
 		Ok(Self { response, body })
@@ -19,6 +31,27 @@ impl Response {
 		&self.response.stats
 	}
 
+	/// Statistics for reading the body so far
+	#[must_use]
+	pub fn body_stats(&self) -> BodyStats {
+		self.body.stats()
+	}
+
+	/// Details of the TLS connection the response was received on, such as
+	/// the negotiated version, cipher suite, ALPN protocol and the server's
+	/// certificates
+	#[must_use]
+	pub const fn tls_info(&self) -> Option<&TlsInfo> {
+		self.response.tls.as_ref()
+	}
+
+	/// Statistics for each redirect followed or authentication challenge
+	/// answered before the final request, in order
+	#[must_use]
+	pub fn hops(&self) -> &[Stats] {
+		&self.response.hops
+	}
+
 	#[must_use]
 	pub const fn version(&self) -> Version {
 		self.response.version
@@ -39,11 +72,39 @@ impl Response {
 		self.response.url.as_ref()
 	}
 
+	pub fn etag(&self) -> Result<Option<ETag>> {
+		self.headers().get_parsed(header::ETAG)
+	}
+
+	pub fn last_modified(&self) -> Result<Option<SystemTime>> {
+		self.headers().get_date(header::LAST_MODIFIED)
+	}
+
+	pub fn date(&self) -> Result<Option<SystemTime>> {
+		self.headers().get_date(header::DATE)
+	}
+
+	pub fn content_length(&self) -> Result<Option<u64>> {
+		self.headers().get_parsed(header::CONTENT_LENGTH)
+	}
+
+	pub fn content_type(&self) -> Result<Option<MediaType>> {
+		self.headers().get_parsed(header::CONTENT_TYPE)
+	}
+
+	pub fn content_range(&self) -> Result<Option<ContentRange>> {
+		ContentRange::from_headers(self.headers())
+	}
+
 	#[must_use]
 	pub fn into_body(self) -> Body {
 		self.body
 	}
 
+	pub fn multipart(self) -> Result<Multipart> {
+		Multipart::from_response(self)
+	}
+
 	pub fn body(&mut self) -> &mut Body {
 		&mut self.body
 	}
@@ -58,13 +119,60 @@ impl Response {
 		Ok(bytes)
 	}
 
-	pub async fn text(&mut self) -> Result<String> {
-		let mut string = String::new();
+	/// The encoding of the body, from its byte order mark or the `charset`
+	/// parameter of `Content-Type`. Defaults to UTF-8
+	fn encoding(&self, bytes: &[u8], lossy: bool) -> Result<(&'static Encoding, usize)> {
+		if let Some(bom) = Encoding::for_bom(bytes) {
+			return Ok(bom);
+		}
+
+		let charset = match self.content_type() {
+			Ok(content_type) => content_type.and_then(|content_type| content_type.charset()),
+			Err(_) if lossy => None,
+			Err(err) => return Err(err)
+		};
+
+		let Some(charset) = charset else {
+			return Ok((UTF_8, 0));
+		};
+
+		match Encoding::for_label(charset.trim().as_bytes()) {
+			Some(encoding) => Ok((encoding, 0)),
+			None if lossy => {
+				debug!(target: self, "== Unknown charset '{}', decoding as UTF-8", charset);
+
+				Ok((UTF_8, 0))
+			}
+
+			None => Err(HttpError::UnknownCharset(charset).into())
+		}
+	}
 
-		self.body().read_to_string(&mut string).await?;
+	async fn decode(&mut self, lossy: bool) -> Result<String> {
+		let bytes = self.bytes().await?;
+		let (encoding, bom) = self.encoding(&bytes, lossy)?;
+		let bytes = &bytes[bom..];
 
-		check_interrupt().await?;
+		let text = if lossy {
+			encoding.decode_without_bom_handling(bytes).0
+		} else {
+			encoding
+				.decode_without_bom_handling_and_without_replacement(bytes)
+				.ok_or(HttpError::InvalidText(encoding.name()))?
+		};
+
+		Ok(text.into_owned())
+	}
+
+	/// Reads the body as text, decoding it with the charset of the response.
+	/// Fails if the body contains invalid sequences for the charset
+	pub async fn text(&mut self) -> Result<String> {
+		self.decode(false).await
+	}
 
-		Ok(string)
+	/// Like [`Self::text`], but replaces invalid sequences with U+FFFD and
+	/// falls back to UTF-8 for unknown charsets
+	pub async fn text_lossy(&mut self) -> Result<String> {
+		self.decode(true).await
 	}
 }
@@ -0,0 +1,262 @@
+use std::io::SeekFrom;
+
+use super::*;
+
+const DEFAULT_READ_AHEAD: u64 = 1024 * 1024;
+
+/* forward seeks shorter than this read and discard data instead of making a
+ * new request */
+const SKIP_THRESHOLD: u64 = 64 * 1024;
+
+/// A body that supports [`Seek`] by issuing range requests on demand. If the
+/// request has a range set, positions are relative to its start and reads
+/// stop at its end
+pub struct SeekableBody {
+	request: Request,
+	body: Option<Body>,
+	offset: u64,
+	body_end: u64,
+	position: u64,
+	len: u64,
+	content_type: Option<String>,
+	read_ahead: Option<u64>
+}
+
+#[asynchronous]
+impl SeekableBody {
+	pub(super) async fn new(mut request: Request) -> Result<Self> {
+		let offset = request.options.start.unwrap_or(0);
+		let limit = request.options.end;
+		let probe_end = offset.saturating_add(DEFAULT_READ_AHEAD);
+
+		request.options.start = Some(offset);
+		request.options.end = Some(limit.map_or(probe_end, |limit| limit.min(probe_end)));
+
+		let (response, reader) = transfer(&mut request, None).await?;
+
+		/* an empty resource can't satisfy any range */
+		if response.status == StatusCode::RANGE_NOT_SATISFIABLE {
+			match ContentRange::unsatisfied_total(&response.headers)? {
+				Some(total) if total <= offset => {
+					return Ok(Self {
+						request,
+						body: None,
+						offset,
+						body_end: 0,
+						position: 0,
+						len: 0,
+						content_type: None,
+						read_ahead: Some(DEFAULT_READ_AHEAD)
+					});
+				}
+
+				_ => return Err(HttpError::RangeMismatch.into())
+			}
+		}
+
+		check_range(&request, &response)?;
+
+		if response.status != StatusCode::PARTIAL_CONTENT {
+			return Err(HttpError::RangeNotSupported.into());
+		}
+
+		if response
+			.headers
+			.get_str(header::ACCEPT_RANGES)?
+			.is_some_and(|ranges| ranges.eq_ignore_ascii_case("none"))
+		{
+			return Err(HttpError::RangeNotSupported.into());
+		}
+
+		let range = ContentRange::from_headers(&response.headers)?
+			.ok_or(HttpError::MissingHeader(header::CONTENT_RANGE))?;
+		let len = range.total.ok_or_else(|| {
+			let value = response.headers.get_str(header::CONTENT_RANGE);
+
+			HttpError::InvalidHeader(
+				header::CONTENT_RANGE,
+				value.ok().flatten().unwrap_or_default().to_string()
+			)
+		})?;
+
+		let len = limit.map_or(len, |limit| limit.min(len)).saturating_sub(offset);
+
+		if let Some(validator) = range_validator(&response.headers) {
+			request.headers.insert(header::IF_RANGE, validator)?;
+		}
+
+		let content_type = response
+			.headers
+			.get_str(header::CONTENT_TYPE)?
+			.map(ToString::to_string);
+
+		let body = Body::new(reader, &request, &response)?;
+
+		Ok(Self {
+			request,
+			body: Some(body),
+			offset,
+			body_end: range.end.saturating_sub(offset),
+			position: 0,
+			len,
+			content_type,
+			read_ahead: Some(DEFAULT_READ_AHEAD)
+		})
+	}
+
+	/// Sets how many bytes past the current position each range request
+	/// fetches. `None` requests everything up to the end of the resource
+	pub fn set_read_ahead(&mut self, read_ahead: Option<u64>) -> &mut Self {
+		self.read_ahead = read_ahead.filter(|read_ahead| *read_ahead > 0);
+		self
+	}
+
+	#[must_use]
+	pub const fn len(&self) -> u64 {
+		self.len
+	}
+
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// The `Content-Type` of the resource, from the first response
+	#[must_use]
+	pub fn content_type(&self) -> Option<&str> {
+		self.content_type.as_deref()
+	}
+
+	async fn request_range(&mut self) -> Result<()> {
+		let end = match self.read_ahead {
+			Some(read_ahead) => self.position.saturating_add(read_ahead).min(self.len),
+			None => self.len
+		};
+
+		let (start, end) = (
+			self.offset.saturating_add(self.position),
+			self.offset.saturating_add(end)
+		);
+
+		debug!(target: &*self, "== Requesting range {}-{}", start, end);
+
+		self.request.options.start = Some(start);
+		self.request.options.end = Some(end);
+
+		let (response, reader) = transfer(&mut self.request, None).await?;
+
+		check_range(&self.request, &response)?;
+
+		if response.status != StatusCode::PARTIAL_CONTENT {
+			return Err(HttpError::RangeMismatch.into());
+		}
+
+		let range = ContentRange::from_headers(&response.headers)?
+			.ok_or(HttpError::MissingHeader(header::CONTENT_RANGE))?;
+
+		self.body = Some(Body::new(reader, &self.request, &response)?);
+		self.body_end = range.end.saturating_sub(self.offset);
+
+		Ok(())
+	}
+
+	async fn skip(&mut self, mut amount: u64) -> Result<()> {
+		let mut discard = [0u8; 4096];
+
+		while amount > 0 {
+			let len = amount.try_into().unwrap_or(usize::MAX).min(discard.len());
+			let read = self.read(&mut discard[0..len]).await?;
+
+			if read == 0 {
+				break;
+			}
+
+			#[allow(clippy::arithmetic_side_effects)]
+			(amount -= read as u64);
+		}
+
+		Ok(())
+	}
+}
+
+#[asynchronous]
+impl Read for SeekableBody {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		#[allow(clippy::arithmetic_side_effects)]
+		let remaining = self.len - self.position;
+
+		read_into!(buf, remaining.try_into().unwrap_or(usize::MAX));
+
+		loop {
+			let Some(body) = &mut self.body else {
+				self.request_range().await?;
+
+				continue;
+			};
+
+			let read = body.read(buf).await?;
+
+			if read == 0 {
+				self.body = None;
+
+				if self.position >= self.body_end {
+					continue;
+				}
+
+				return Err(UrlError::PartialFile.into());
+			}
+
+			#[allow(clippy::arithmetic_side_effects)]
+			(self.position += read as u64);
+
+			break Ok(read);
+		}
+	}
+}
+
+#[asynchronous]
+impl Seek for SeekableBody {
+	async fn seek(&mut self, seek: SeekFrom) -> Result<u64> {
+		let pos = match seek {
+			SeekFrom::Current(rel) => self.position.checked_add_signed(rel),
+			SeekFrom::Start(pos) => Some(pos),
+			SeekFrom::End(pos) => self.len.checked_add_signed(pos)
+		}
+		.ok_or_else(|| fmt_error!("Invalid seek to a negative position" @ ErrorKind::InvalidInput))?
+		.min(self.len);
+
+		if self.body.is_some() && pos >= self.position && pos < self.body_end {
+			#[allow(clippy::arithmetic_side_effects)]
+			let distance = pos - self.position;
+
+			if distance <= SKIP_THRESHOLD {
+				self.skip(distance).await?;
+
+				return Ok(self.position);
+			}
+		}
+
+		if pos != self.position {
+			self.body = None;
+			self.position = pos;
+		}
+
+		Ok(self.position)
+	}
+
+	fn stream_len_fast(&self) -> bool {
+		true
+	}
+
+	async fn stream_len(&mut self) -> Result<u64> {
+		Ok(self.len)
+	}
+
+	fn stream_position_fast(&self) -> bool {
+		true
+	}
+
+	async fn stream_position(&mut self) -> Result<u64> {
+		Ok(self.position)
+	}
+}
@@ -6,12 +6,23 @@ use crate::tls;
 
 #[derive(Default, Clone, Copy)]
 pub struct Stats {
+	/// Time spent on previous hops before the final request
 	pub redirect: Option<Duration>,
 	pub connect: Option<ConnectStats>,
 	pub tls_connect: Option<Duration>,
+	pub tls_resumed: bool,
+
+	/// The request was sent as TLS 1.3 early data and accepted
+	pub early_data: bool,
 	pub stall: Duration,
 	pub wait: Duration,
-	pub response: Duration
+	pub response: Duration,
+	pub cache: Option<CacheStatus>,
+
+	/* bytes transferred, excluding the response body */
+	pub request_header_bytes: u64,
+	pub request_body_bytes: u64,
+	pub response_header_bytes: u64
 }
 
 impl From<ConnectStats> for Stats {
@@ -24,19 +35,41 @@ impl fmt::Debug for Stats {
 	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let mut stats = fmt.debug_struct("Stats");
 
+		if let Some(redirect) = &self.redirect {
+			stats.field("redirect", redirect);
+		}
+
 		if let Some(connect) = &self.connect {
 			stats.field("lookup", &connect.dns_resolve);
 			stats.field("connect", &connect.tcp_connect);
 			stats.field("tries", &connect.tcp_tries);
+
+			if let Some(addr) = &connect.remote_addr {
+				stats.field("remote", addr);
+			}
+
+			if let Some(addr) = &connect.local_addr {
+				stats.field("local", addr);
+			}
 		}
 
 		if let Some(tls) = &self.tls_connect {
 			stats.field("tls", &tls);
+			stats.field("tls_resumed", &self.tls_resumed);
+			stats.field("early_data", &self.early_data);
 		}
 
 		stats.field("stall", &self.stall);
 		stats.field("wait", &self.wait);
 		stats.field("response", &self.response);
+		stats.field("request_headers", &self.request_header_bytes);
+		stats.field("request_body", &self.request_body_bytes);
+		stats.field("response_headers", &self.response_header_bytes);
+
+		if let Some(cache) = &self.cache {
+			stats.field("cache", cache);
+		}
+
 		stats.finish()
 	}
 }
@@ -46,7 +79,35 @@ impl From<tls::conn::ConnectStats> for Stats {
 		Self {
 			connect: Some(connect.stats),
 			tls_connect: Some(connect.tls_connect),
+			tls_resumed: connect.resumed,
+			early_data: connect.early_data,
 			..Default::default()
 		}
 	}
 }
+
+/// Statistics for reading a response body
+#[derive(Default, Clone, Copy, Debug)]
+pub struct BodyStats {
+	/// Bytes of the decoded body, excluding chunk framing
+	pub bytes: u64,
+
+	/// Time from the end of the headers to the end of the body, or until now
+	/// if the body hasn't been read completely
+	pub duration: Duration
+}
+
+impl BodyStats {
+	/// Average bytes per second
+	#[must_use]
+	#[allow(clippy::cast_precision_loss)]
+	pub fn throughput(&self) -> f64 {
+		let secs = self.duration.as_secs_f64();
+
+		if secs > 0.0 {
+			self.bytes as f64 / secs
+		} else {
+			0.0
+		}
+	}
+}
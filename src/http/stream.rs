@@ -9,6 +9,7 @@ use xx_pulse::net::*;
 use super::*;
 use crate::net::conn::Conn;
 use crate::tls::conn::*;
+use crate::tls::server::TlsServerConn;
 
 #[asynchronous(impl(mut, box))]
 pub trait ConnExtra {
@@ -109,13 +110,28 @@ impl_conn!(StreamSocket);
 
 impl_conn!(Conn);
 
-impl_extra!(TlsReadHalf<'a>);
-impl_extra!(TlsWriteHalf<'a>);
+macro_rules! impl_tls_half {
+	($type:ident, $half:ident) => {
+		#[asynchronous]
+		impl<S: TlsSession> ConnExtra for $type<'_, S> {
+			async fn poll(&mut self, flags: BitFlags<PollFlag>) -> Result<BitFlags<PollFlag>> {
+				Self::poll(self, flags).await
+			}
+
+			async fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+				Self::shutdown(self, how).await
+			}
+		}
+
+		impl<S: TlsSession> $half for $type<'_, S> {}
+	};
+}
 
-impl WriteHalf for TlsWriteHalf<'_> {}
-impl ReadHalf for TlsReadHalf<'_> {}
+impl_tls_half!(TlsReadHalf, ReadHalf);
+impl_tls_half!(TlsWriteHalf, WriteHalf);
 
 impl_conn!(TlsConn);
+impl_conn!(TlsServerConn);
 
 pub struct HttpConn {
 	inner: Box<dyn Connection + Send + Sync>
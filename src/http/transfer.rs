@@ -1,17 +1,23 @@
 #![allow(unreachable_pub)]
 
 use std::str::{from_utf8, FromStr};
+use std::sync::Arc;
 
+use rustls::ClientConfig;
 use url::Position;
 
 use super::*;
 use crate::net::conn::*;
-use crate::tls::conn::TlsConn;
+use crate::tls::config::ALPN_HTTP11;
+use crate::tls::conn::{TlsConn, TlsInfo};
+use crate::tls::danger::DangerousVerifier;
 
 /* maximum allowed Content-Length header if we want to reuse a connection for
  * redirect instead of closing it and opening a new one */
 const REDIRECT_REUSE_THRESHOLD: u64 = 4 * 1024;
 
+const PAYLOAD_CHUNK_SIZE: usize = 16 * 1024;
+
 pub const DEFAULT_MAXIMUM_HEADER_SIZE: u32 = 128 * 1024;
 
 #[derive(Clone)]
@@ -23,12 +29,22 @@ pub struct Options {
 	pub recvbuf_size: Option<i32>,
 	pub sendbuf_size: Option<i32>,
 	pub secure: bool,
+	pub danger_accept_invalid_certs: bool,
+	pub danger_accept_invalid_hostnames: bool,
+	pub early_data: bool,
+	pub allow_tls_truncation: bool,
 
 	/* http options */
 	pub min_version: Version,
 	pub max_version: Version,
 	pub follow_redirect: u32,
-	pub maximum_header_size: u32
+	pub maximum_header_size: u32,
+	pub parse_mode: ParseMode,
+	pub start: Option<u64>,
+	pub end: Option<u64>,
+	pub max_body_size: Option<u64>,
+	pub download_rate: Option<u64>,
+	pub upload_rate: Option<u64>
 }
 
 impl Options {
@@ -41,11 +57,21 @@ impl Options {
 			recvbuf_size: None,
 			sendbuf_size: None,
 			secure: false,
+			danger_accept_invalid_certs: false,
+			danger_accept_invalid_hostnames: false,
+			early_data: false,
+			allow_tls_truncation: false,
 
 			min_version: Version::Http10,
 			max_version: Version::Http11,
 			follow_redirect: 5,
-			maximum_header_size: DEFAULT_MAXIMUM_HEADER_SIZE
+			maximum_header_size: DEFAULT_MAXIMUM_HEADER_SIZE,
+			parse_mode: ParseMode::Strict,
+			start: None,
+			end: None,
+			max_body_size: None,
+			download_rate: None,
+			upload_rate: None
 		}
 	}
 }
@@ -55,7 +81,14 @@ pub struct Request {
 	pub(crate) request: RequestBase,
 	pub(crate) method: Method,
 	pub(crate) headers: Headers,
-	pub(crate) body: Option<Payload>
+	pub(crate) body: Option<Payload>,
+	pub(crate) auth: Option<Auth>,
+	pub(crate) cache: Option<Cache>,
+	pub(crate) upload_progress: Option<ProgressCallback>,
+	pub(crate) download_progress: Option<ProgressCallback>,
+	pub(crate) listener: Option<EventListener>,
+	pub(crate) har: Option<HarRecorder>,
+	pub(crate) tls_config: Option<Arc<ClientConfig>>
 }
 
 impl Request {
@@ -65,7 +98,14 @@ impl Request {
 			request,
 			method,
 			headers: Headers::new(),
-			body: None
+			body: None,
+			auth: None,
+			cache: None,
+			upload_progress: None,
+			download_progress: None,
+			listener: None,
+			har: None,
+			tls_config: None
 		}
 	}
 
@@ -110,12 +150,181 @@ impl Request {
 		self.body = Some(payload.into());
 		self
 	}
+
+	pub fn start(&mut self, start: u64) -> &mut Self {
+		self.options.start = Some(start);
+		self
+	}
+
+	pub fn end(&mut self, end: u64) -> &mut Self {
+		self.options.end = Some(end);
+		self
+	}
+
+	/// Defaults to [`ParseMode::Strict`]
+	pub fn set_parse_mode(&mut self, mode: ParseMode) -> &mut Self {
+		self.options.parse_mode = mode;
+		self
+	}
+
+	/// Fail reading the body once it exceeds `size` bytes
+	pub fn set_max_body_size(&mut self, size: u64) -> &mut Self {
+		self.options.max_body_size = Some(size);
+		self
+	}
+
+	/// Limit downloading the body to `rate` bytes per second
+	pub fn set_download_rate(&mut self, rate: u64) -> &mut Self {
+		self.options.download_rate = Some(rate);
+		self
+	}
+
+	/// Limit uploading the payload to `rate` bytes per second
+	pub fn set_upload_rate(&mut self, rate: u64) -> &mut Self {
+		self.options.upload_rate = Some(rate);
+		self
+	}
+
+	/// Call `callback` as the payload is sent
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn on_upload_progress(
+		&mut self, callback: impl Fn(&Progress) + Send + Sync + 'static
+	) -> &mut Self {
+		self.upload_progress = Some(Arc::new(callback));
+		self
+	}
+
+	/// Call `callback` as the response body is read
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn on_download_progress(
+		&mut self, callback: impl Fn(&Progress) + Send + Sync + 'static
+	) -> &mut Self {
+		self.download_progress = Some(Arc::new(callback));
+		self
+	}
+
+	/// Call `callback` at each stage of the request, from resolving the
+	/// host to reading the end of the body
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn on_event(&mut self, callback: impl Fn(&Event<'_>) + Send + Sync + 'static) -> &mut Self {
+		self.listener = Some(Arc::new(callback));
+		self
+	}
+
+	/// Record each exchange, including redirects, in `recorder`
+	pub fn set_har_recorder(&mut self, recorder: HarRecorder) -> &mut Self {
+		self.har = Some(recorder);
+		self
+	}
+
+	pub fn basic_auth(&mut self, username: &str, password: &str) -> &mut Self {
+		self.auth = Some(Auth::new(Credentials::Basic {
+			username: username.to_string(),
+			password: password.to_string()
+		}));
+
+		self
+	}
+
+	pub fn bearer_auth(&mut self, token: &str) -> &mut Self {
+		self.auth = Some(Auth::new(Credentials::Bearer(token.to_string())));
+		self
+	}
+
+	pub fn digest_auth(&mut self, username: &str, password: &str) -> &mut Self {
+		self.auth = Some(Auth::new(Credentials::Digest {
+			username: username.to_string(),
+			password: password.to_string()
+		}));
+
+		self
+	}
+
+	pub fn if_match(&mut self, etag: &ETag) -> &mut Self {
+		self.header(header::IF_MATCH, etag.to_string().as_str())
+	}
+
+	pub fn if_none_match(&mut self, etag: &ETag) -> &mut Self {
+		self.header(header::IF_NONE_MATCH, etag.to_string().as_str())
+	}
+
+	pub fn if_modified_since(&mut self, time: SystemTime) -> &mut Self {
+		self.header(header::IF_MODIFIED_SINCE, format_date(time).as_str())
+	}
+
+	pub fn if_unmodified_since(&mut self, time: SystemTime) -> &mut Self {
+		self.header(header::IF_UNMODIFIED_SINCE, format_date(time).as_str())
+	}
+
+	/// Use `config` instead of the shared default for TLS connections, for
+	/// example to present a client certificate built with
+	/// [`TlsConfig`](crate::tls::config::TlsConfig)
+	pub fn set_tls_config(&mut self, config: Arc<ClientConfig>) -> &mut Self {
+		self.tls_config = Some(config);
+		self
+	}
+
+	/// Accept any server certificate. Only for testing, every connection
+	/// logs a warning. Requests with a custom TLS config fail, use
+	/// [`TlsConfig::danger_accept_invalid_certs`] instead
+	///
+	/// [`TlsConfig::danger_accept_invalid_certs`]: crate::tls::config::TlsConfig::danger_accept_invalid_certs
+	pub fn danger_accept_invalid_certs(&mut self, accept: bool) -> &mut Self {
+		self.options.danger_accept_invalid_certs = accept;
+		self
+	}
+
+	/// Accept certificates trusted by the system roots but issued for a
+	/// different hostname. Only for testing, every connection logs a warning.
+	/// Requests with a custom TLS config fail, use
+	/// [`TlsConfig::danger_accept_invalid_hostnames`] instead
+	///
+	/// [`TlsConfig::danger_accept_invalid_hostnames`]: crate::tls::config::TlsConfig::danger_accept_invalid_hostnames
+	pub fn danger_accept_invalid_hostnames(&mut self, accept: bool) -> &mut Self {
+		self.options.danger_accept_invalid_hostnames = accept;
+		self
+	}
+
+	/// Send the request as TLS 1.3 early data when resuming a session. Only
+	/// applies to idempotent requests without a payload, since early data may
+	/// be replayed
+	pub fn set_early_data(&mut self, enable: bool) -> &mut Self {
+		self.options.early_data = enable;
+		self
+	}
+
+	/// Accept connections closed without a TLS close_notify, for servers
+	/// known to skip it. Otherwise bodies read until the connection closes
+	/// fail with `TlsError::Truncated` when cut short
+	pub fn allow_tls_truncation(&mut self, allow: bool) -> &mut Self {
+		self.options.allow_tls_truncation = allow;
+		self
+	}
+
+	/// Serve responses from `cache` when possible, and store responses in it
+	pub fn set_cache(&mut self, cache: Cache) -> &mut Self {
+		self.cache = Some(cache);
+		self
+	}
+}
+
+/// Collects the request head to send as early data
+struct RequestBuffer(Vec<u8>);
+
+#[asynchronous]
+impl Write for RequestBuffer {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		self.0.extend_from_slice(buf);
+
+		Ok(buf.len())
+	}
 }
 
 #[asynchronous]
 async fn get_connection_for(
-	request: &Request, url: &Url, _connection_pool: /* TOOD */ Option<()>
-) -> Result<(HttpConn, Option<Stats>)> {
+	request: &Request, url: &Url, early_data: Option<&[u8]>,
+	_connection_pool: /* TOOD */ Option<()>
+) -> Result<(HttpConn, Option<Stats>, Option<TlsInfo>)> {
 	let mut options = ConnectOptions::new(
 		url.host_str().unwrap(),
 		url.port().unwrap_or(request.options.port)
@@ -126,6 +335,7 @@ async fn get_connection_for(
 	options.set_timeout(request.options.timeout);
 	options.set_tcp_nodelay(true);
 	options.set_tcp_keepalive(60);
+	options.set_listener(request.listener.clone());
 
 	if let Some(size) = request.options.recvbuf_size {
 		options.set_recvbuf_size(size);
@@ -143,24 +353,139 @@ async fn get_connection_for(
 		debug!(target: request, "== Using default port {}", default);
 	}
 
-	let (stream, stats) = if request.options.secure {
-		let (conn, stats) = TlsConn::connect_stats(&options).await?;
+	let (stream, stats, info) = if request.options.secure {
+		let mut config = match &request.tls_config {
+			Some(config) => config.clone(),
+			None => get_tls_client_config().await
+		};
+
+		let (certs, hostnames) = (
+			request.options.danger_accept_invalid_certs,
+			request.options.danger_accept_invalid_hostnames
+		);
+
+		if certs || hostnames {
+			/* a built config's roots, pins and revocation checks can't be
+			 * recovered, so they would be silently replaced */
+			if request.tls_config.is_some() {
+				return Err(fmt_error!(
+					"Set the danger options on the TlsConfig of a custom TLS config" @
+					ErrorKind::InvalidInput
+				));
+			}
+
+			config = DangerousVerifier::install(&config, get_root_certs().await, certs, hostnames)?;
+		}
+
+		let (mut conn, stats) = match early_data {
+			Some(data) => {
+				if !config.enable_early_data {
+					let mut early = config.as_ref().clone();
+
+					early.enable_early_data = true;
+					config = Arc::new(early);
+				}
+
+				TlsConn::connect_stats_early_data(&options, config, data).await?
+			}
+
+			None => TlsConn::connect_stats_config(&options, config).await?
+		};
+
+		conn.set_allow_truncation(request.options.allow_tls_truncation);
+
+		/* only HTTP/1.1 is spoken over the connection */
+		if let Some(protocol) = conn.alpn_protocol().filter(|protocol| *protocol != ALPN_HTTP11) {
+			let protocol = String::from_utf8_lossy(protocol).into_owned();
+
+			debug!(target: request, "== Server selected unsupported protocol '{}'", protocol);
 
-		(HttpConn::new(conn), stats.into())
+			return Err(HttpError::UnsupportedProtocol(protocol).into());
+		}
+
+		let info = conn.info();
+
+		(HttpConn::new(conn), stats.into(), Some(info))
 	} else {
 		let (conn, stats) = Conn::connect_stats(&options).await?;
 
-		(HttpConn::new(conn), stats.into())
+		(HttpConn::new(conn), stats.into(), None)
 	};
 
-	Ok((stream, Some(stats)))
+	Ok((stream, Some(stats), info))
+}
+
+/// Writes the payload in chunks, applying the upload rate limit and reporting
+/// progress
+#[asynchronous]
+#[allow(clippy::impl_trait_in_params)]
+async fn write_payload(
+	writer: &mut BufWriter<impl Write>, request: &Request, body: &mut PayloadRepr
+) -> Result<u64> {
+	let total = match body {
+		PayloadRepr::Bytes(bytes) => Some(bytes.len() as u64),
+		PayloadRepr::Stream(_) => None
+	};
+
+	let mut rate = request.options.upload_rate.map(RateLimiter::new);
+	let mut progress = request
+		.upload_progress
+		.clone()
+		.map(|callback| ProgressTracker::new(callback, total));
+
+	let mut buf = Vec::new();
+	let mut offset = 0;
+	let mut written = 0u64;
+
+	loop {
+		let len = match &mut rate {
+			Some(rate) => rate.acquire(PAYLOAD_CHUNK_SIZE).await?,
+			None => PAYLOAD_CHUNK_SIZE
+		};
+
+		let chunk = match body {
+			PayloadRepr::Bytes(bytes) => {
+				let chunk = &bytes[offset..];
+				let chunk = &chunk[0..len.min(chunk.len())];
+
+				#[allow(clippy::arithmetic_side_effects)]
+				(offset += chunk.len());
+
+				chunk
+			}
+
+			PayloadRepr::Stream(stream) => {
+				buf.resize(len, 0);
+
+				let read = stream.read(&mut buf[0..len]).await?;
+
+				&buf[0..read]
+			}
+		};
+
+		if let Some(rate) = &mut rate {
+			#[allow(clippy::arithmetic_side_effects)]
+			rate.release(len - chunk.len());
+		}
+
+		if chunk.is_empty() {
+			break Ok(written);
+		}
+
+		writer.write_all(chunk).await?;
+		written = written.saturating_add(chunk.len() as u64);
+
+		if let Some(progress) = &mut progress {
+			progress.update(chunk.len());
+		}
+	}
 }
 
 #[asynchronous]
 #[allow(clippy::impl_trait_in_params)]
 async fn send_request(
 	writer: &mut BufWriter<impl Write>, request: &Request, version: Version, url: &Url,
-	body: &mut Option<Payload>
+	body: &mut Option<Payload>, stats: &mut Stats
 ) -> Result<()> {
 	macro_rules! http_write {
 		($writer: expr, $($arg: tt)*) => {{
@@ -187,20 +512,44 @@ async fn send_request(
 		}
 	}?;
 
+	#[allow(clippy::arithmetic_side_effects)]
+	let mut header_bytes = request.method.as_str().len() + path.len() + 3;
+
+	if version != Version::Http09 {
+		#[allow(clippy::arithmetic_side_effects)]
+		(header_bytes += version.as_str().len() + 1);
+	}
+
 	for (key, value) in &request.headers {
-		trace!(target: request, "<< {}: {}", key.as_str(), value.to_str().unwrap_or("<binary>"));
+		if value.is_sensitive() {
+			trace!(target: request, "<< {}: <sensitive>", key.as_str());
+		} else {
+			trace!(target: request, "<< {}: {}", key.as_str(), value.to_str().unwrap_or("<binary>"));
+		}
 
 		writer.write_fmt(format_args!("{}: ", key.as_str())).await?;
 		writer.write_all(value.as_bytes()).await?;
 		writer.write_string("\r\n").await?;
+
+		#[allow(clippy::arithmetic_side_effects)]
+		(header_bytes += key.as_str().len() + value.len() + 4);
 	}
 
 	writer.write_string("\r\n").await?;
 
+	#[allow(clippy::arithmetic_side_effects)]
+	(stats.request_header_bytes = header_bytes as u64 + 2);
+
 	if let Some(Payload(body)) = body {
-		let _ = match body {
-			PayloadRepr::Bytes(bytes) => writer.write_all(bytes).await?,
-			PayloadRepr::Stream(stream) => writer.pipe_from(stream.as_mut()).await?
+		let throttled = request.options.upload_rate.is_some() || request.upload_progress.is_some();
+
+		stats.request_body_bytes = match body {
+			PayloadRepr::Bytes(bytes) if !throttled => {
+				writer.write_all(bytes).await?;
+				bytes.len() as u64
+			}
+
+			body => write_payload(writer, request, body).await?
 		};
 
 		check_interrupt().await?;
@@ -275,21 +624,47 @@ fn parse_status_line(line: &str) -> Option<(Version, StatusCode)> {
 	Some((version, StatusCode::from_str(split.next()?).ok()?))
 }
 
+/// Checks that a line read by [`read_line_in_place`] ended with CRLF
+fn check_line_ending(line: &str, offset: usize) -> Result<()> {
+	if offset.saturating_sub(line.len()) < 2 {
+		return Err(HttpError::BareLineFeed.into());
+	}
+
+	Ok(())
+}
+
 #[asynchronous]
 #[allow(clippy::impl_trait_in_params)]
 pub async fn read_header_line_limited(
-	reader: &mut impl BufRead
+	reader: &mut impl BufRead, mode: ParseMode
 ) -> Result<Option<(HeaderName, Option<HeaderValue>, usize)>> {
 	let (line, offset) = read_line_in_place(reader).await?;
+	let strict = mode == ParseMode::Strict;
+
+	if strict {
+		check_line_ending(line, offset)?;
+	}
 
 	let result = if line.is_empty() {
 		None
 	} else {
+		if strict && line.starts_with([' ', '\t']) {
+			return Err(HttpError::ObsoleteLineFolding.into());
+		}
+
 		let (key, value) = match line.split_once(':') {
 			Some((key, value)) => (key, Some(value)),
+			None if strict => {
+				return Err(HttpError::MissingHeaderSeparator(line.to_string()).into())
+			}
+
 			None => (line, None)
 		};
 
+		if strict && key.trim_end() != key {
+			return Err(HttpError::HeaderNameWhitespace(line.to_string()).into());
+		}
+
 		let key = key.trim().try_into_name()?;
 		let value = if let Some(value) = value {
 			Some(value.trim_start().try_into_value()?)
@@ -308,11 +683,14 @@ pub async fn read_header_line_limited(
 #[asynchronous]
 #[allow(clippy::impl_trait_in_params)]
 pub async fn read_headers_limited<T>(
-	reader: &mut impl BufRead, headers: &mut Headers, mut size_limit: usize, log: &T
-) -> Result<()> {
+	reader: &mut impl BufRead, headers: &mut Headers, limit: usize, mode: ParseMode, log: &T
+) -> Result<usize> {
+	let mut size_limit = limit;
+
 	loop {
-		let (key, value, read) = match read_header_line_limited(reader).await? {
-			None => break Ok(()),
+		let (key, value, read) = match read_header_line_limited(reader, mode).await? {
+			#[allow(clippy::arithmetic_side_effects)]
+			None => break Ok(limit - size_limit),
 			Some(header) => header
 		};
 
@@ -333,6 +711,13 @@ pub async fn read_headers_limited<T>(
 			trace!(target: log, ">> {}: {:?}", key.as_str(), value);
 		}
 
+		if mode == ParseMode::Strict &&
+			key == header::CONTENT_LENGTH &&
+			headers.get(header::CONTENT_LENGTH).is_some_and(|len| *len != value)
+		{
+			break Err(HttpError::DuplicateContentLength.into());
+		}
+
 		headers.insert(key, value)?;
 	}
 }
@@ -341,7 +726,7 @@ pub async fn read_headers_limited<T>(
 #[allow(clippy::impl_trait_in_params)]
 pub async fn parse_response(
 	reader: &mut impl BufRead, request: &Request, headers: &mut Headers
-) -> Result<(StatusCode, Version)> {
+) -> Result<(StatusCode, Version, usize)> {
 	let mut total_size = 0;
 
 	let prefix_matches = {
@@ -356,12 +741,25 @@ pub async fn parse_response(
 		prefix == &reader.buffer()[0..prefix.len()]
 	};
 
+	let strict = request.options.parse_mode == ParseMode::Strict;
+
 	let (version, status) = if !prefix_matches {
+		if strict {
+			let (line, _) = read_line_in_place(reader).await?;
+
+			return Err(HttpError::InvalidStatusLine(line.to_string()).into());
+		}
+
 		warn!(target: request, "Invalid status line, assuming HTTP 0.9");
 
 		(Version::Http09, StatusCode::OK)
 	} else {
 		let (line, offset) = read_line_in_place(reader).await?;
+
+		if strict {
+			check_line_ending(line, offset)?;
+		}
+
 		let result =
 			parse_status_line(line).ok_or_else(|| HttpError::InvalidStatusLine(line.to_string()));
 
@@ -379,23 +777,36 @@ pub async fn parse_response(
 	}
 
 	if version == Version::Http09 {
-		return Ok((status, version));
+		return Ok((status, version, total_size));
 	}
 
 	match (request.options.maximum_header_size as usize).checked_sub(total_size) {
-		Some(limit) => read_headers_limited(reader, headers, limit, request).await?,
+		Some(limit) => {
+			let mode = request.options.parse_mode;
+
+			#[allow(clippy::arithmetic_side_effects)]
+			(total_size += read_headers_limited(reader, headers, limit, mode, request).await?);
+		}
+
 		None => return Err(HttpError::HeadersTooLong.into())
 	}
 
-	Ok((status, version))
+	Ok((status, version, total_size))
 }
 
 pub struct RawResponse {
 	pub stats: Stats,
+
+	/// Details of the TLS connection, if the response was received over one
+	pub tls: Option<TlsInfo>,
+
+	/// Stats for each redirected or challenged request, in order
+	pub hops: Vec<Stats>,
 	pub version: Version,
 	pub status: StatusCode,
 	pub headers: Headers,
-	pub url: Option<Url>
+	pub url: Option<Url>,
+	pub har_entry: Option<usize>
 }
 
 #[asynchronous]
@@ -411,6 +822,14 @@ pub async fn transfer(
 			.insert(header::HOST, req_url.host_str().unwrap())?;
 	}
 
+	if let Some(range) = range_header(&request.options)? {
+		request.headers.insert(header::RANGE, range.as_str())?;
+	}
+
+	if request.auth.is_none() {
+		request.auth = Auth::from_url(req_url);
+	}
+
 	let req_url = request.request.url().unwrap();
 
 	let mut body = request.body.take();
@@ -421,22 +840,66 @@ pub async fn transfer(
 
 	let mut response_headers = Headers::new();
 
+	let transfer_start = Instant::now();
+	let mut hops = Vec::new();
+
 	loop {
+		let hop_start = transfer_start.elapsed();
+
 		debug!(target: &*request, "== Starting request for '{}'", url.as_str());
 
 		response_headers.clear();
 
-		let (conn, stats) = get_connection_for(request, url, connection_pool).await?;
+		let started = SystemTime::now();
+
+		if let Some(auth) = &mut request.auth {
+			auth.authorize(&mut request.headers, &request.method, url, req_url)?;
+		}
+
+		let mut early_stats = Stats::default();
+
+		/* the request head is sent with the handshake */
+		let early_data = if request.options.early_data &&
+			request.options.secure &&
+			request.method.is_idempotent() &&
+			body.is_none()
+		{
+			let mut writer = BufWriter::new(RequestBuffer(Vec::new()));
+
+			send_request(&mut writer, request, version, url, &mut body, &mut early_stats).await?;
+
+			Some(writer.into_parts().0 .0)
+		} else {
+			None
+		};
+
+		let (conn, stats, tls) =
+			get_connection_for(request, url, early_data.as_deref(), connection_pool).await?;
 		let mut stats = stats.unwrap_or_default();
 
 		let conn = {
-			let mut writer = BufWriter::new(conn);
 			let stall = Instant::now();
 
-			send_request(&mut writer, request, version, url, &mut body).await?;
+			let conn = if early_data.is_some() {
+				stats.request_header_bytes = early_stats.request_header_bytes;
+
+				conn
+			} else {
+				let mut writer = BufWriter::new(conn);
+
+				send_request(&mut writer, request, version, url, &mut body, &mut stats).await?;
+
+				writer.into_parts().0
+			};
 
 			stats.stall = stall.elapsed();
-			writer.into_parts().0
+
+			emit(
+				request.listener.as_ref(),
+				Event::RequestSent { url, headers: &request.headers }
+			);
+
+			conn
 		};
 
 		let (mut response, reader) = {
@@ -446,31 +909,76 @@ pub async fn transfer(
 			reader.fill().await?;
 			stats.wait = start.elapsed();
 
-			let (status, version) =
+			emit(request.listener.as_ref(), Event::FirstByte { elapsed: stats.wait });
+
+			let (status, version, header_bytes) =
 				parse_response(&mut reader, request, &mut response_headers).await?;
 
 			stats.response = start.elapsed();
+			stats.response_header_bytes = header_bytes as u64;
 
 			(
 				RawResponse {
 					stats,
+					tls,
+					hops: Vec::new(),
 					version,
 					status,
 					headers: response_headers,
-					url: None
+					url: None,
+					har_entry: None
 				},
 				reader
 			)
 		};
 
+		macro_rules! record {
+			($redirect:expr) => {
+				if let Some(har) = &request.har {
+					response.har_entry = har.record(
+						request,
+						url,
+						version,
+						body.as_ref(),
+						&response,
+						$redirect,
+						started
+					);
+				}
+			};
+		}
+
+		/* a streamed payload was consumed by the first attempt */
+		let consumed = body.as_ref().is_some_and(|body| !body.is_rewindable());
+
+		if response.status == StatusCode::UNAUTHORIZED && request.auth.is_some() && consumed {
+			debug!(target: &*request, "== Not answering challenge, the payload was streamed");
+		}
+
+		if response.status == StatusCode::UNAUTHORIZED && !consumed {
+			if let Some(auth) = &mut request.auth {
+				if auth.challenged(&response.headers)? {
+					record!(None);
+
+					debug!(target: &*request, "== Answering authentication challenge");
+
+					hops.push(response.stats);
+					response_headers = response.headers;
+
+					continue;
+				}
+			}
+		}
+
 		if redirects_remaining > 0 && response.status.is_redirection() {
 			if let Some(location) = response.headers.get_str(header::LOCATION)? {
 				#[allow(clippy::arithmetic_side_effects)]
 				(redirects_remaining -= 1);
 
-				let body = Body::new(reader, request, &response)?;
+				/* the redirect body is discarded, so the size limit doesn't apply */
+				let redirect_body = Body::with_max_size(reader, request, &response, None)?;
 
-				if body
+				if redirect_body
 					.remaining()
 					.is_some_and(|len| len < REDIRECT_REUSE_THRESHOLD)
 				{
@@ -481,6 +989,15 @@ pub async fn transfer(
 					.join(location)
 					.map_err(|_| UrlError::InvalidRedirectUrl(location.to_string()))?;
 
+				emit(
+					request.listener.as_ref(),
+					Event::Redirect { from: url, to: &new_url, status: response.status }
+				);
+
+				record!(Some(&new_url));
+
+				hops.push(response.stats);
+
 				url = redirected_url.insert(new_url);
 
 				if url.scheme() != req_url.scheme() {
@@ -493,9 +1010,111 @@ pub async fn transfer(
 			}
 		}
 
+		record!(None);
+
+		if !hops.is_empty() {
+			response.stats.redirect = Some(hop_start);
+			response.hops = hops;
+		}
+
 		request.body = body;
 		response.url = redirected_url;
 
 		break Ok((response, reader));
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Data(&'static [u8]);
+
+	#[asynchronous]
+	impl Read for Data {
+		async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+			let read = read_into_slice(buf, self.0);
+
+			self.0 = &self.0[read..];
+
+			Ok(read)
+		}
+	}
+
+	#[asynchronous]
+	async fn read_headers(data: &'static [u8], mode: ParseMode) -> Result<Headers> {
+		let mut reader = BufReader::new(Data(data));
+		let mut headers = Headers::new();
+
+		read_headers_limited(&mut reader, &mut headers, 1024, mode, &()).await?;
+
+		Ok(headers)
+	}
+
+	#[main]
+	#[test]
+	async fn test_strict_headers() -> Result<()> {
+		let data = b"Host: a\r\nContent-Length: 1\r\n\r\n";
+		let headers = read_headers(data, ParseMode::Strict).await?;
+
+		assert_eq!(headers.get_str(header::HOST)?, Some("a"));
+		assert_eq!(headers.get_str(header::CONTENT_LENGTH)?, Some("1"));
+
+		let rejected: [&'static [u8]; 5] = [
+			b"Host: a\n\r\n",
+			b"Host\r\n\r\n",
+			b"Host : a\r\n\r\n",
+			b"Host: a\r\n b\r\n\r\n",
+			b"Content-Length: 1\r\nContent-Length: 2\r\n\r\n"
+		];
+
+		for data in rejected {
+			assert!(read_headers(data, ParseMode::Strict).await.is_err());
+		}
+
+		Ok(())
+	}
+
+	#[main]
+	#[test]
+	async fn test_lenient_headers() -> Result<()> {
+		let headers = read_headers(b"Host : a\nAccept\r\n\n", ParseMode::Lenient).await?;
+
+		assert_eq!(headers.get_str(header::HOST)?, Some("a"));
+		assert_eq!(headers.get_str(header::ACCEPT)?, Some(""));
+
+		let data = b"Content-Length: 1\r\nContent-Length: 2\r\n\r\n";
+		let headers = read_headers(data, ParseMode::Lenient).await?;
+
+		assert_eq!(headers.get_str(header::CONTENT_LENGTH)?, Some("2"));
+
+		Ok(())
+	}
+
+	#[main]
+	#[test]
+	async fn test_headers_too_long() -> Result<()> {
+		let data = b"Host: a\r\nAccept: b\r\n\r\n";
+		let mut reader = BufReader::new(Data(data));
+		let mut headers = Headers::new();
+
+		let result =
+			read_headers_limited(&mut reader, &mut headers, 12, ParseMode::Strict, &()).await;
+
+		assert!(result.is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_status_line() {
+		assert_eq!(
+			parse_status_line("HTTP/1.1 404 Not Found"),
+			Some((Version::Http11, StatusCode::NOT_FOUND))
+		);
+		assert_eq!(parse_status_line("HTTP/1.0 200"), Some((Version::Http10, StatusCode::OK)));
+		assert_eq!(parse_status_line("HTTP/1.1"), None);
+		assert_eq!(parse_status_line("HTTP/4.2 200 OK"), None);
+		assert_eq!(parse_version("HTTP/11"), None);
+	}
+}
@@ -1,9 +1,12 @@
 pub mod dns;
 pub mod env;
 pub mod error;
+pub mod events;
 pub mod file;
 pub mod http;
 pub mod net;
+pub mod progress;
+pub mod stream;
 pub mod tls;
 pub mod ws;
 
@@ -15,4 +18,7 @@ use xx_pulse::*;
 
 use self::env::*;
 use self::error::*;
+use self::events::*;
+use self::progress::*;
 use self::request::*;
+pub use self::stream::{fetch, open};
@@ -21,7 +21,9 @@ use crate::dns::{LookupIp, Resolver};
 pub struct ConnectStats {
 	pub dns_resolve: Duration,
 	pub tcp_tries: u32,
-	pub tcp_connect: Duration
+	pub tcp_connect: Duration,
+	pub remote_addr: Option<SocketAddr>,
+	pub local_addr: Option<SocketAddr>
 }
 
 #[derive(Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -43,7 +45,8 @@ pub struct ConnectOptions<'host> {
 	recvbuf_size: Option<i32>,
 	sendbuf_size: Option<i32>,
 	tcp_nodelay: bool,
-	tcp_keepalive: Option<i32>
+	tcp_keepalive: Option<i32>,
+	listener: Option<EventListener>
 }
 
 impl<'host> ConnectOptions<'host> {
@@ -64,7 +67,8 @@ impl<'host> ConnectOptions<'host> {
 			recvbuf_size: None,
 			sendbuf_size: None,
 			tcp_nodelay: false,
-			tcp_keepalive: None
+			tcp_keepalive: None,
+			listener: None
 		}
 	}
 
@@ -112,6 +116,17 @@ impl<'host> ConnectOptions<'host> {
 		self.tcp_keepalive = Some(idle);
 		self
 	}
+
+	/// Report DNS and connect events to `listener`
+	pub fn set_listener(&mut self, listener: Option<EventListener>) -> &mut Self {
+		self.listener = listener;
+		self
+	}
+
+	#[must_use]
+	pub const fn listener(&self) -> Option<&EventListener> {
+		self.listener.as_ref()
+	}
 }
 
 pub struct Conn {
@@ -155,7 +170,8 @@ impl Conn {
 		let start = Instant::now();
 
 		for ip in addrs {
-			let addr = SocketAddr::new(ip, options.port).into();
+			let socket_addr = SocketAddr::new(ip, options.port);
+			let addr = socket_addr.into();
 			let socket =
 				Socket::new_for_addr(&addr, SocketType::Stream as u32, IpProtocol::Tcp).await?;
 			let connection = Self { inner: socket };
@@ -165,13 +181,29 @@ impl Conn {
 
 			debug!(target: &connection, "<< Connecting to {}:{} - Try {}: {}", options.host, options.port, stats.tcp_tries, ip);
 
+			emit(
+				options.listener(),
+				Event::ConnectStart { addr: socket_addr, attempt: stats.tcp_tries }
+			);
+
 			let now = Instant::now();
+			let result = connection.inner.connect(&addr).await;
+
+			emit(
+				options.listener(),
+				Event::ConnectEnd {
+					addr: socket_addr,
+					elapsed: now.elapsed(),
+					error: result.as_ref().err()
+				}
+			);
 
-			match connection.inner.connect(&addr).await {
+			match result {
 				Ok(()) => {
 					let elapsed = start.elapsed();
 
 					stats.tcp_connect = elapsed;
+					stats.remote_addr = Some(socket_addr);
 
 					debug!(target: &connection, ">> Connected to {} ({:.3} ms elapsed, {:.3} ms total)", options.host, now.elapsed().as_secs_f32() * 1000.0, elapsed.as_secs_f32() * 1000.0);
 
@@ -211,11 +243,29 @@ impl Conn {
 		let mut stats = ConnectStats::default();
 
 		let addrs = {
+			emit(options.listener(), Event::DnsStart { host: options.host });
+
 			let now = Instant::now();
-			let addrs = options.resolver.resolve_ips(options.host).await?;
+			let result = options.resolver.resolve_ips(options.host).await;
 
 			stats.dns_resolve = now.elapsed();
-			addrs
+
+			#[allow(clippy::arithmetic_side_effects)]
+			let addresses = result
+				.as_ref()
+				.map_or(0, |addrs| addrs.v4().len() + addrs.v6().len());
+
+			emit(
+				options.listener(),
+				Event::DnsEnd {
+					host: options.host,
+					addresses,
+					elapsed: stats.dns_resolve,
+					error: result.as_ref().err()
+				}
+			);
+
+			result?
 		};
 
 		let connection = match options.timeout {
@@ -226,6 +276,8 @@ impl Conn {
 				.ok_or(common::CONNECT_TIMEOUT)??
 		};
 
+		stats.local_addr = connection.inner.local_addr().await.ok();
+
 		if let Some(size) = options.recvbuf_size {
 			connection.inner.set_recvbuf_size(size).await?;
 		}
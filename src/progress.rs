@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A snapshot of a transfer in progress
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+	/// Bytes transferred so far
+	pub transferred: u64,
+
+	/// Total bytes expected, if known
+	pub total: Option<u64>,
+
+	/// Time since the transfer started
+	pub elapsed: Duration
+}
+
+pub type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+pub(crate) struct ProgressTracker {
+	callback: ProgressCallback,
+	start: Instant,
+	transferred: u64,
+	total: Option<u64>
+}
+
+impl ProgressTracker {
+	pub(crate) fn new(callback: ProgressCallback, total: Option<u64>) -> Self {
+		Self { callback, start: Instant::now(), transferred: 0, total }
+	}
+
+	/// Moves the transferred count to `position`, such as after a seek
+	pub(crate) fn reset(&mut self, position: u64) {
+		self.transferred = position;
+	}
+
+	pub(crate) fn update(&mut self, amount: usize) {
+		self.transferred = self.transferred.saturating_add(amount as u64);
+
+		(self.callback)(&Progress {
+			transferred: self.transferred,
+			total: self.total,
+			elapsed: self.start.elapsed()
+		});
+	}
+}
@@ -0,0 +1,223 @@
+use std::io::SeekFrom;
+
+use ::http::header;
+use xx_core::async_std::io::*;
+use xx_core::coroutines::Task;
+
+use super::*;
+use crate::file::FileStream;
+use crate::http::{Body, HttpError, SeekableBody};
+
+pub struct Metadata {
+	pub url: Url,
+	pub len: Option<u64>,
+	pub content_type: Option<String>
+}
+
+enum StreamRepr {
+	File(FileStream),
+	Http(Body),
+	Seekable(SeekableBody)
+}
+
+/// A stream over the contents of a url, regardless of scheme
+pub struct UrlStream {
+	repr: StreamRepr,
+	metadata: Metadata
+}
+
+impl UrlStream {
+	#[must_use]
+	pub const fn metadata(&self) -> &Metadata {
+		&self.metadata
+	}
+
+	#[must_use]
+	pub const fn len(&self) -> Option<u64> {
+		self.metadata.len
+	}
+
+	#[must_use]
+	pub const fn is_seekable(&self) -> bool {
+		!matches!(self.repr, StreamRepr::Http(_))
+	}
+}
+
+#[asynchronous]
+impl Read for UrlStream {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		match &mut self.repr {
+			StreamRepr::File(file) => file.read(buf).await,
+			StreamRepr::Http(body) => body.read(buf).await,
+			StreamRepr::Seekable(body) => body.read(buf).await
+		}
+	}
+}
+
+#[asynchronous]
+impl Seek for UrlStream {
+	async fn seek(&mut self, seek: SeekFrom) -> Result<u64> {
+		match &mut self.repr {
+			StreamRepr::File(file) => file.seek(seek).await,
+			StreamRepr::Seekable(body) => body.seek(seek).await,
+			StreamRepr::Http(_) => Err(UrlError::NotSeekable.into())
+		}
+	}
+
+	fn stream_len_fast(&self) -> bool {
+		self.is_seekable()
+	}
+
+	async fn stream_len(&mut self) -> Result<u64> {
+		match &mut self.repr {
+			StreamRepr::File(file) => file.stream_len().await,
+			StreamRepr::Seekable(body) => body.stream_len().await,
+			StreamRepr::Http(_) => Err(UrlError::NotSeekable.into())
+		}
+	}
+
+	fn stream_position_fast(&self) -> bool {
+		self.is_seekable()
+	}
+
+	async fn stream_position(&mut self) -> Result<u64> {
+		match &mut self.repr {
+			StreamRepr::File(file) => file.stream_position().await,
+			StreamRepr::Seekable(body) => body.stream_position().await,
+			StreamRepr::Http(_) => Err(UrlError::NotSeekable.into())
+		}
+	}
+}
+
+pub struct FetchRequest {
+	inner: RequestBase,
+	start: Option<u64>,
+	end: Option<u64>,
+	seekable: bool
+}
+
+#[asynchronous]
+impl FetchRequest {
+	fn new(url: &str, seekable: bool) -> Self {
+		Self {
+			inner: RequestBase::new(url, |scheme| {
+				matches!(scheme, "file" | "http" | "https")
+			}),
+			start: None,
+			end: None,
+			seekable
+		}
+	}
+
+	pub fn start(&mut self, start: u64) -> &mut Self {
+		self.start = Some(start);
+		self
+	}
+
+	/// Ignored for seekable `http` and `https` streams
+	pub fn end(&mut self, end: u64) -> &mut Self {
+		self.end = Some(end);
+		self
+	}
+
+	async fn open_file(&self, url: Url) -> Result<UrlStream> {
+		let mut request = crate::file::get(url.as_str());
+
+		if let Some(start) = self.start {
+			request.start(start);
+		}
+
+		if let Some(end) = self.end {
+			request.end(end);
+		}
+
+		let mut file = request.run().await?;
+		let len = file.stream_len().await?;
+
+		Ok(UrlStream {
+			repr: StreamRepr::File(file),
+			metadata: Metadata { url, len: Some(len), content_type: None }
+		})
+	}
+
+	async fn open_http(&self, url: Url) -> Result<UrlStream> {
+		let mut request = crate::http::get(url.as_str());
+
+		if self.seekable {
+			let mut body = request.seekable().await?;
+
+			if let Some(start) = self.start {
+				body.seek(SeekFrom::Start(start)).await?;
+			}
+
+			let len = body.len();
+			let content_type = body.content_type().map(ToString::to_string);
+
+			return Ok(UrlStream {
+				repr: StreamRepr::Seekable(body),
+				metadata: Metadata { url, len: Some(len), content_type }
+			});
+		}
+
+		if let Some(start) = self.start {
+			request.start(start);
+		}
+
+		if let Some(end) = self.end {
+			request.end(end);
+		}
+
+		let response = request.run().await?;
+
+		if !response.status().is_success() {
+			return Err(HttpError::UnexpectedStatus(response.status()).into());
+		}
+
+		let content_type = response
+			.headers()
+			.get_str(header::CONTENT_TYPE)?
+			.map(ToString::to_string);
+
+		let url = response.url().cloned().unwrap_or(url);
+		let body = response.into_body();
+		let len = body.remaining();
+
+		Ok(UrlStream {
+			repr: StreamRepr::Http(body),
+			metadata: Metadata { url, len, content_type }
+		})
+	}
+
+	pub async fn run(&mut self) -> Result<UrlStream> {
+		let url = self.inner.finalize()?.clone();
+
+		match url.scheme() {
+			"file" => self.open_file(url).await,
+			_ => self.open_http(url).await
+		}
+	}
+}
+
+#[asynchronous(task)]
+impl Task for FetchRequest {
+	type Output = Result<UrlStream>;
+
+	async fn run(mut self) -> Self::Output {
+		FetchRequest::run(&mut self).await
+	}
+}
+
+/// Fetch the contents of `url` as a forward only stream
+#[must_use]
+#[allow(clippy::impl_trait_in_params)]
+pub fn fetch(url: impl AsRef<str>) -> FetchRequest {
+	FetchRequest::new(url.as_ref(), false)
+}
+
+/// Open `url` as a seekable stream. For `http` and `https`, the server must
+/// support range requests
+#[must_use]
+#[allow(clippy::impl_trait_in_params)]
+pub fn open(url: impl AsRef<str>) -> FetchRequest {
+	FetchRequest::new(url.as_ref(), true)
+}
@@ -4,22 +4,37 @@ use rustls::pki_types::CertificateDer;
 use rustls::RootCertStore;
 use rustls_pemfile::certs;
 use xx_core::async_std::AsyncIteratorExt;
-use xx_core::{debug, trace};
+use xx_core::{debug, trace, warn};
 use xx_pulse::*;
 
 use super::*;
 
-#[asynchronous]
-async fn try_load_certs(path: impl AsRef<Path>) -> Result<Vec<CertificateDer<'static>>> {
-	let data = fs::read(path).await?;
-
+/// Parses every certificate in a PEM bundle
+pub fn parse_pem_certs(data: &[u8]) -> Result<Vec<CertificateDer<'static>>> {
 	certs(&mut &data[..])
 		.map(|result| result.map_err(Into::into))
 		.collect()
 }
 
+/// Reads every certificate in a PEM bundle
+#[asynchronous]
+pub async fn load_pem_certs(path: impl AsRef<Path>) -> Result<Vec<CertificateDer<'static>>> {
+	parse_pem_certs(&fs::read(path).await?)
+}
+
+#[asynchronous]
+async fn try_load_ca_file(path: &Path, store: &mut RootCertStore) -> Result<()> {
+	let certs = load_pem_certs(path).await?;
+
+	debug!("++ Loaded {} certificates from {:?}", certs.len(), path);
+
+	store.add_parsable_certificates(certs);
+
+	Ok(())
+}
+
 #[asynchronous]
-async fn try_load_ca_path(path: &str, store: &mut RootCertStore) -> Result<()> {
+async fn try_load_ca_path(path: &Path, store: &mut RootCertStore) -> Result<()> {
 	let mut entries = fs::read_dir(path).await?;
 	let mut certs = Vec::new();
 
@@ -33,26 +48,48 @@ async fn try_load_ca_path(path: &str, store: &mut RootCertStore) -> Result<()> {
 			continue;
 		}
 
-		if let Ok(mut loaded) = try_load_certs(&path).await {
+		if let Ok(mut loaded) = load_pem_certs(&path).await {
 			trace!("++ Loaded {} certs from {:?}", loaded.len(), path);
 
 			certs.append(&mut loaded);
 		}
 	}
 
-	debug!("++ Loaded {} certificates from {}", certs.len(), path);
+	debug!("++ Loaded {} certificates from {:?}", certs.len(), path);
 
 	store.add_parsable_certificates(certs);
 
 	Ok(())
 }
 
+/// Loads the trusted root certificates. `SSL_CERT_FILE` names a PEM bundle
+/// and `SSL_CERT_DIR` a colon separated list of directories of PEM files, as
+/// with OpenSSL. Files and directories that don't exist or can't be read are
+/// skipped with a warning. If neither is set, [`root_certs_path`] is used
 #[asynchronous]
 pub async fn load_system_certs() -> Result<RootCertStore> {
 	let mut root_store = RootCertStore::empty();
-	let path = root_certs_path();
+	let file = std::env::var_os("SSL_CERT_FILE");
+	let dirs = std::env::var_os("SSL_CERT_DIR");
+
+	if let Some(file) = &file {
+		if let Err(err) = try_load_ca_file(Path::new(file), &mut root_store).await {
+			warn!("== Skipping certificate file {:?}: {}", file, err.to_string());
+		}
+	}
 
-	try_load_ca_path(path, &mut root_store).await?;
+	if let Some(dirs) = &dirs {
+		for dir in std::env::split_paths(dirs) {
+			/* like OpenSSL, entries that can't be read are skipped */
+			if let Err(err) = try_load_ca_path(&dir, &mut root_store).await {
+				warn!("== Skipping certificate directory {:?}: {}", dir, err.to_string());
+			}
+		}
+	}
+
+	if file.is_none() && dirs.is_none() {
+		try_load_ca_path(Path::new(root_certs_path()), &mut root_store).await?;
+	}
 
 	Ok(root_store)
 }
@@ -0,0 +1,318 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rustls::client::danger::ServerCertVerifier;
+use rustls::client::Resumption;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ClientConfig, RootCertStore};
+use rustls_pemfile::{certs, private_key};
+use xx_pulse::*;
+
+use super::*;
+use crate::tls::certs::load_pem_certs;
+use crate::tls::danger::DangerousVerifier;
+use crate::tls::pin::{PinMode, PinSet, PinnedVerifier};
+use crate::tls::policy::TlsPolicy;
+use crate::tls::revocation::RevocationConfig;
+
+/// A certificate chain and its private key, presented by a client for mutual
+/// TLS authentication or by a [`TlsServerConn`]
+///
+/// [`TlsServerConn`]: super::server::TlsServerConn
+pub struct Identity {
+	certs: Vec<CertificateDer<'static>>,
+	key: PrivateKeyDer<'static>
+}
+
+#[asynchronous]
+impl Identity {
+	/// Creates an identity from a DER encoded certificate chain, leaf first,
+	/// and its private key
+	pub fn from_der(
+		certs: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>
+	) -> Result<Self> {
+		if certs.is_empty() {
+			return Err(fmt_error!("No certificates provided" @ ErrorKind::InvalidInput));
+		}
+
+		Ok(Self { certs, key })
+	}
+
+	/// Parses a PEM encoded certificate chain and a PKCS#8, PKCS#1 (RSA) or
+	/// SEC1 (EC) private key. Both may come from the same buffer
+	pub fn from_pem(cert_pem: &[u8], key_pem: &[u8]) -> Result<Self> {
+		let chain = certs(&mut &cert_pem[..]).collect::<std::io::Result<Vec<_>>>()?;
+		let key = private_key(&mut &key_pem[..])?
+			.ok_or_else(|| fmt_error!("No private key found" @ ErrorKind::InvalidData))?;
+
+		Self::from_der(chain, key)
+	}
+
+	/// Reads the certificate chain and private key from PEM files
+	pub async fn from_pem_file(
+		cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>
+	) -> Result<Self> {
+		let chain = fs::read(cert_path).await?;
+		let key = fs::read(key_path).await?;
+
+		Self::from_pem(&chain, &key)
+	}
+
+	/// The certificate chain, leaf first
+	#[must_use]
+	pub fn certs(&self) -> &[CertificateDer<'static>] {
+		&self.certs
+	}
+
+	pub(crate) const fn key(&self) -> &PrivateKeyDer<'static> {
+		&self.key
+	}
+}
+
+impl Clone for Identity {
+	fn clone(&self) -> Self {
+		Self { certs: self.certs.clone(), key: self.key.clone_key() }
+	}
+}
+
+/// Number of sessions remembered for resumption by each config. Also the
+/// rustls default, used by the shared default config
+pub const DEFAULT_SESSION_CACHE_SIZE: usize = 256;
+
+/// ALPN identifier for HTTP/1.1
+pub const ALPN_HTTP11: &[u8] = b"http/1.1";
+
+/// ALPN identifier for HTTP/2
+pub const ALPN_H2: &[u8] = b"h2";
+
+/// Builds a [`ClientConfig`] for [`TlsConn::connect_config`] or
+/// `HttpRequest::set_tls_config`. By default the system root certificates
+/// are trusted, see [`load_system_certs`]
+///
+/// [`load_system_certs`]: super::certs::load_system_certs
+/// [`TlsConn::connect_config`]: super::conn::TlsConn::connect_config
+#[derive(Clone)]
+pub struct TlsConfig {
+	identity: Option<Identity>,
+	alpn: Vec<Vec<u8>>,
+	no_system_roots: bool,
+	roots: Vec<CertificateDer<'static>>,
+	root_files: Vec<PathBuf>,
+	pins: Option<(PinSet, PinMode)>,
+	revocation: Option<RevocationConfig>,
+	danger_accept_invalid_certs: bool,
+	danger_accept_invalid_hostnames: bool,
+	session_cache_size: usize,
+	early_data: bool,
+	policy: TlsPolicy
+}
+
+impl Default for TlsConfig {
+	fn default() -> Self {
+		Self {
+			identity: None,
+			alpn: Vec::new(),
+			no_system_roots: false,
+			roots: Vec::new(),
+			root_files: Vec::new(),
+			pins: None,
+			revocation: None,
+			danger_accept_invalid_certs: false,
+			danger_accept_invalid_hostnames: false,
+			session_cache_size: DEFAULT_SESSION_CACHE_SIZE,
+			early_data: false,
+			policy: TlsPolicy::new()
+		}
+	}
+}
+
+#[asynchronous]
+impl TlsConfig {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Authenticate with `identity` when the server requests a client
+	/// certificate
+	pub fn client_identity(&mut self, identity: Identity) -> &mut Self {
+		self.identity = Some(identity);
+		self
+	}
+
+	/// Offer `protocols` with ALPN, in order of preference. The HTTP client
+	/// only speaks HTTP/1.1, and fails with
+	/// [`HttpError::UnsupportedProtocol`] if the server selects another
+	/// protocol
+	///
+	/// [`HttpError::UnsupportedProtocol`]: crate::http::HttpError::UnsupportedProtocol
+	pub fn alpn_protocols(&mut self, protocols: &[&[u8]]) -> &mut Self {
+		self.alpn = protocols.iter().map(|protocol| protocol.to_vec()).collect();
+		self
+	}
+
+	/// Trust `cert` in addition to the system root certificates
+	pub fn add_root_certificate(&mut self, cert: CertificateDer<'static>) -> &mut Self {
+		self.roots.push(cert);
+		self
+	}
+
+	/// Trust every certificate in the PEM bundle at `path`, read when the
+	/// config is built
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn add_root_pem_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+		self.root_files.push(path.into());
+		self
+	}
+
+	/// Only trust the root certificates added to this config
+	pub fn disable_system_roots(&mut self) -> &mut Self {
+		self.no_system_roots = true;
+		self
+	}
+
+	/// Require the server's certificates to match one of `pins`
+	pub fn pin(&mut self, pins: PinSet, mode: PinMode) -> &mut Self {
+		self.pins = Some((pins, mode));
+		self
+	}
+
+	/// Check the server's certificate against CRLs and stapled OCSP responses
+	pub fn revocation(&mut self, revocation: RevocationConfig) -> &mut Self {
+		self.revocation = Some(revocation);
+		self
+	}
+
+	/// Accept any server certificate, including expired, self-signed and
+	/// wrongly named ones. Only for testing, every connection logs a warning.
+	/// Building fails if pins or revocation checks are also configured
+	pub fn danger_accept_invalid_certs(&mut self, accept: bool) -> &mut Self {
+		self.danger_accept_invalid_certs = accept;
+		self
+	}
+
+	/// Accept trusted certificates issued for a different hostname. Only for
+	/// testing, every connection logs a warning. Building fails if pins or
+	/// revocation checks are also configured
+	pub fn danger_accept_invalid_hostnames(&mut self, accept: bool) -> &mut Self {
+		self.danger_accept_invalid_hostnames = accept;
+		self
+	}
+
+	/// Remember up to `size` sessions (TLS 1.2 tickets and TLS 1.3 PSKs) for
+	/// resumption. The cache is shared by every connection using the built
+	/// config. Zero disables resumption
+	pub fn session_cache_size(&mut self, size: usize) -> &mut Self {
+		self.session_cache_size = size;
+		self
+	}
+
+	/// Allow sending TLS 1.3 early data when resuming a session, see
+	/// [`TlsConn::connect_stats_early_data`]
+	///
+	/// [`TlsConn::connect_stats_early_data`]: super::conn::TlsConn::connect_stats_early_data
+	pub fn enable_early_data(&mut self, enable: bool) -> &mut Self {
+		self.early_data = enable;
+		self
+	}
+
+	/// Restrict the negotiable TLS versions, cipher suites and key exchange
+	/// groups. Impossible combinations fail when building
+	pub fn policy(&mut self, policy: TlsPolicy) -> &mut Self {
+		self.policy = policy;
+		self
+	}
+
+	async fn verifier(
+		&self, roots: &Arc<RootCertStore>
+	) -> Result<Option<Arc<dyn ServerCertVerifier>>> {
+		if self.danger_accept_invalid_certs || self.danger_accept_invalid_hostnames {
+			if self.pins.is_some() || self.revocation.is_some() {
+				return Err(fmt_error!(
+					"Insecure verification can't be combined with pins or revocation checks" @
+					ErrorKind::InvalidInput
+				));
+			}
+
+			return Ok(Some(Arc::new(DangerousVerifier::new(
+				roots.clone(),
+				self.danger_accept_invalid_certs,
+				self.danger_accept_invalid_hostnames
+			)?)));
+		}
+
+		let revocation: Option<Arc<dyn ServerCertVerifier>> = match &self.revocation {
+			Some(revocation) => Some(Arc::new(revocation.verifier(roots.clone()).await?)),
+			None => None
+		};
+
+		if let Some((pins, mode)) = &self.pins {
+			let pinned = match revocation {
+				Some(inner) => {
+					PinnedVerifier::with_verifier(pins.clone(), *mode, roots.clone(), inner)?
+				}
+
+				None => PinnedVerifier::new(pins.clone(), *mode, roots.clone())?
+			};
+
+			return Ok(Some(Arc::new(pinned)));
+		}
+
+		Ok(revocation)
+	}
+
+	async fn root_store(&self) -> Result<Arc<RootCertStore>> {
+		if !self.no_system_roots && self.roots.is_empty() && self.root_files.is_empty() {
+			return Ok(get_root_certs().await);
+		}
+
+		let mut store = if self.no_system_roots {
+			RootCertStore::empty()
+		} else {
+			get_root_certs().await.as_ref().clone()
+		};
+
+		for cert in &self.roots {
+			store.add(cert.clone()).map_err(Error::new)?;
+		}
+
+		for path in &self.root_files {
+			for cert in load_pem_certs(path).await? {
+				store.add(cert).map_err(Error::new)?;
+			}
+		}
+
+		if store.is_empty() {
+			return Err(fmt_error!("No trusted root certificates" @ ErrorKind::InvalidInput));
+		}
+
+		Ok(Arc::new(store))
+	}
+
+	pub async fn build(&self) -> Result<Arc<ClientConfig>> {
+		let roots = self.root_store().await?;
+		let builder = self.policy.client_builder()?;
+
+		let builder = match self.verifier(&roots).await? {
+			Some(verifier) => builder.dangerous().with_custom_certificate_verifier(verifier),
+			None => builder.with_root_certificates(roots)
+		};
+
+		let mut config = match &self.identity {
+			Some(identity) => builder
+				.with_client_auth_cert(identity.certs.clone(), identity.key.clone_key())
+				.map_err(Error::new)?,
+			None => builder.with_no_client_auth()
+		};
+
+		config.alpn_protocols.clone_from(&self.alpn);
+		config.enable_early_data = self.early_data;
+		config.resumption = if self.session_cache_size == 0 {
+			Resumption::disabled()
+		} else {
+			Resumption::in_memory_sessions(self.session_cache_size)
+		};
+
+		Ok(Arc::new(config))
+	}
+}
@@ -1,10 +1,15 @@
 #![allow(unsafe_code)]
 
+use std::fmt;
 use std::io::{self, IoSlice};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use rustls::{ClientConfig, ClientConnection};
+use rustls::pki_types::CertificateDer;
+use rustls::{
+	CipherSuite, ClientConfig, ClientConnection, ConnectionCommon, HandshakeKind, IoState,
+	ProtocolVersion, ServerConnection, SupportedCipherSuite
+};
 use x509_parser::prelude::*;
 use xx_core::async_std::io::*;
 use xx_core::async_std::sync::Mutex;
@@ -18,12 +23,59 @@ use xx_core::{debug, trace};
 use xx_pulse::net::*;
 
 use super::*;
+use crate::http::stream::ConnExtra;
 use crate::net::conn::{self, Conn, ConnectOptions};
+use crate::tls::error::TlsError;
+use crate::tls::revocation::{take_status, RevocationStatus};
+
+/// Parameters negotiated during the TLS handshake
+#[derive(Default, Clone)]
+pub struct TlsInfo {
+	pub version: Option<ProtocolVersion>,
+	pub cipher: Option<CipherSuite>,
+	pub alpn: Option<Vec<u8>>,
+
+	/// The certificate chain presented by the server, leaf first
+	pub peer_certificates: Vec<CertificateDer<'static>>,
+
+	/// Whether the server's certificate is revoked, if checked with a
+	/// [`RevocationVerifier`]. `None` for resumed sessions
+	///
+	/// [`RevocationVerifier`]: super::revocation::RevocationVerifier
+	pub revocation: Option<RevocationStatus>
+}
+
+impl fmt::Debug for TlsInfo {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut info = fmt.debug_struct("TlsInfo");
+
+		info.field("version", &self.version);
+		info.field("cipher", &self.cipher);
+
+		if let Some(alpn) = &self.alpn {
+			info.field("alpn", &String::from_utf8_lossy(alpn));
+		}
+
+		info.field("peer_certificates", &self.peer_certificates.len());
+
+		if let Some(revocation) = &self.revocation {
+			info.field("revocation", revocation);
+		}
+
+		info.finish()
+	}
+}
 
 #[derive(Default, Clone, Copy)]
 pub struct ConnectStats {
 	pub stats: conn::ConnectStats,
-	pub tls_connect: Duration
+	pub tls_connect: Duration,
+
+	/// The session was resumed from an earlier connection
+	pub resumed: bool,
+
+	/// The server accepted data sent as TLS 1.3 early data
+	pub early_data: bool
 }
 
 impl From<conn::ConnectStats> for ConnectStats {
@@ -32,31 +84,32 @@ impl From<conn::ConnectStats> for ConnectStats {
 	}
 }
 
-struct Adapter<'a> {
-	connection: &'a mut Conn,
-	context: &'a Context,
-	flags: BitFlags<MessageFlag>
+/// Blocking [`io::Read`] and [`io::Write`] over a socket, for rustls
+struct Adapter<'a, T> {
+	connection: &'a mut T,
+	context: &'a Context
 }
 
-impl<'a> Adapter<'a> {
+impl<'a, T> Adapter<'a, T> {
 	/// # Safety
 	/// Calls to io functions must be allowed to suspend
-	unsafe fn new(connection: &'a mut Conn, context: &'a Context) -> Self {
-		Self { connection, context, flags: BitFlags::default() }
+	unsafe fn new(connection: &'a mut T, context: &'a Context) -> Self {
+		Self { connection, context }
 	}
 }
 
-impl io::Read for Adapter<'_> {
+impl<T: Read> io::Read for Adapter<'_, T> {
 	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
 		/* Safety: guaranteed by caller */
-		unsafe { scoped(self.context, self.connection.recv(buf, self.flags)) }.map_err(Into::into)
+		unsafe { scoped(self.context, Read::read(&mut *self.connection, buf)) }.map_err(Into::into)
 	}
 }
 
-impl io::Write for Adapter<'_> {
+impl<T: Write> io::Write for Adapter<'_, T> {
 	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
 		/* Safety: guaranteed by caller */
-		unsafe { scoped(self.context, self.connection.send(buf, self.flags)) }.map_err(Into::into)
+		unsafe { scoped(self.context, Write::write(&mut *self.connection, buf)) }
+			.map_err(Into::into)
 	}
 
 	fn flush(&mut self) -> io::Result<()> {
@@ -65,19 +118,178 @@ impl io::Write for Adapter<'_> {
 
 	fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
 		/* Safety: guaranteed by caller */
-		unsafe {
-			scoped(
-				self.context,
-				self.connection.send_vectored(bufs, self.flags)
-			)
+		unsafe { scoped(self.context, Write::write_vectored(&mut *self.connection, bufs)) }
+			.map_err(Into::into)
+	}
+}
+
+/// Drives the handshake of a client or server connection to completion.
+/// `process` is called in place of [`ConnectionCommon::process_new_packets`]
+#[asynchronous]
+pub(super) async fn handshake<T: Read + Write + ConnExtra, D>(
+	connection: &mut T, tls: &mut ConnectionCommon<D>,
+	mut process: impl FnMut(&mut ConnectionCommon<D>) -> std::result::Result<IoState, rustls::Error>
+) -> Result<()> {
+	let mut eof = false;
+
+	/* Safety: we are in an async function */
+	let mut adapter = unsafe { Adapter::new(connection, get_context().await) };
+
+	loop {
+		let handshaking = tls.is_handshaking();
+
+		/* poll to prevent hang when either read or write don't get through */
+		let mut flags = BitFlags::default();
+
+		if tls.wants_write() {
+			flags |= PollFlag::Out;
+		}
+
+		if tls.wants_read() {
+			flags |= PollFlag::In;
+		}
+
+		let flags = adapter.connection.poll(flags).await?;
+
+		if flags.intersects(PollFlag::Out) && tls.write_tls(&mut adapter)? == 0 {
+			eof = true;
+		}
+
+		if !handshaking && !eof {
+			break;
+		}
+
+		if flags.intersects(PollFlag::In) {
+			if tls.read_tls(&mut adapter)? == 0 {
+				eof = true;
+			} else {
+				process(tls).map_err(Error::new)?;
+			}
+		}
+
+		if handshaking && !tls.is_handshaking() && tls.wants_write() {
+			continue;
+		}
+
+		match (eof, handshaking, tls.is_handshaking()) {
+			(_, true, false) | (_, false, _) => break,
+			(true, true, true) => {
+				return Err(fmt_error!("EOF during TLS handshake" @ ErrorKind::UnexpectedEof))
+			}
+			(..) => ()
+		}
+	}
+
+	Ok(())
+}
+
+/// Reads plaintext with `read`, receiving records from `connection` until
+/// some is available
+#[asynchronous]
+pub(super) async fn tls_read<T: Read, S: TlsSession>(
+	connection: &mut T, tls: &mut S, allow_truncation: bool,
+	mut read: impl FnMut(&mut S) -> io::Result<usize>
+) -> Result<usize> {
+	match read(tls) {
+		Ok(0) => (),
+		Ok(n) => return Ok(n),
+		Err(err) if err.kind() == io::ErrorKind::WouldBlock => (),
+		Err(err) => return check_eof(Err(err), allow_truncation)
+	}
+
+	/* Safety: we are in an async function */
+	let mut adapter = unsafe { Adapter::new(connection, get_context().await) };
+
+	loop {
+		if tls.read_tls(&mut adapter)? == 0 {
+			/* the reader knows whether close_notify arrived before EOF */
+			return check_eof(read(tls), allow_truncation);
+		}
+
+		let state = tls.process_new_packets().map_err(Error::new)?;
+
+		if state.plaintext_bytes_to_read() == 0 {
+			check_interrupt().await?;
+
+			continue;
+		}
+
+		break Ok(read(tls)?);
+	}
+}
+
+/// Writes plaintext with `write`, sending the resulting records on
+/// `connection`
+#[asynchronous]
+pub(super) async fn tls_write<T: Write, S: TlsSession>(
+	connection: &mut T, tls: &mut S, write: impl Fn(&mut S) -> io::Result<usize>
+) -> Result<usize> {
+	/* Safety: we are in an async function */
+	let mut adapter = unsafe { Adapter::new(connection, get_context().await) };
+
+	loop {
+		let wrote = write(tls)?;
+
+		while tls.wants_write() {
+			if tls.write_tls(&mut adapter)? == 0 {
+				return Ok(wrote);
+			}
+
+			check_interrupt_if_zero(wrote).await?;
+		}
+
+		if wrote != 0 {
+			break Ok(wrote);
+		}
+	}
+}
+
+/// Sends a close_notify alert on `connection`, unless `sent` is already set
+#[asynchronous]
+pub(super) async fn send_close_notify<T: Write, S: TlsSession>(
+	connection: &mut T, tls: &mut S, sent: &mut bool
+) -> Result<()> {
+	if *sent {
+		return Ok(());
+	}
+
+	*sent = true;
+	tls.send_close_notify();
+
+	/* Safety: we are in an async function */
+	let mut adapter = unsafe { Adapter::new(connection, get_context().await) };
+
+	while tls.wants_write() {
+		if tls.write_tls(&mut adapter)? == 0 {
+			break;
 		}
-		.map_err(Into::into)
+	}
+
+	Ok(())
+}
+
+/// Maps the reader's result after the socket reached EOF. rustls reports a
+/// missing close_notify as [`io::ErrorKind::UnexpectedEof`]
+pub(super) fn check_eof(result: io::Result<usize>, allow_truncation: bool) -> Result<usize> {
+	match result {
+		Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+			if allow_truncation {
+				Ok(0)
+			} else {
+				Err(TlsError::Truncated.into())
+			}
+		}
+
+		result => Ok(result?)
 	}
 }
 
 pub struct TlsConn {
 	connection: Conn,
-	tls: ClientConnection
+	tls: ClientConnection,
+	revocation: Option<RevocationStatus>,
+	allow_truncation: bool,
+	close_notify_sent: bool
 }
 
 #[asynchronous]
@@ -89,69 +301,36 @@ impl TlsConn {
 
 		#[asynchronous]
 		pub async fn poll(&mut self, flags: BitFlags<PollFlag>) -> Result<BitFlags<PollFlag>>;
-
-		#[asynchronous]
-		pub async fn shutdown(&mut self, how: Shutdown) -> Result<()>;
-
-		#[asynchronous]
-		pub async fn close(self) -> Result<()>;
 	}
 
 	async fn tls_connect(&mut self, stats: &mut ConnectStats) -> Result<()> {
 		let now = Instant::now();
-		let mut eof = false;
+		let mut revocation = None;
 
-		/* Safety: we are in an async function */
-		let mut adapter = unsafe { Adapter::new(&mut self.connection, get_context().await) };
+		let process = |tls: &mut ConnectionCommon<_>| {
+			/* the verifier runs synchronously within process_new_packets, so a
+			 * status recorded during the call belongs to this connection */
+			take_status();
 
-		loop {
-			let handshaking = self.tls.is_handshaking();
+			let state = tls.process_new_packets();
 
-			/* poll to prevent hang when either read or write don't get through */
-			let mut flags = BitFlags::default();
+			revocation = take_status().or(revocation);
+			state
+		};
 
-			if self.tls.wants_write() {
-				flags |= PollFlag::Out;
-			}
+		let result = handshake(&mut self.connection, &mut self.tls, process).await;
 
-			if self.tls.wants_read() {
-				flags |= PollFlag::In;
-			}
+		self.revocation = revocation;
 
-			let flags = adapter.connection.poll(flags).await?;
+		if let Err(err) = result {
+			/* we don't want to wait for writes in error state */
+			let mut alert = Vec::new();
 
-			if flags.intersects(PollFlag::Out) && self.tls.write_tls(&mut adapter)? == 0 {
-				eof = true;
-			}
-
-			if !handshaking && !eof {
-				break;
+			if self.tls.write_tls(&mut alert).is_ok() && !alert.is_empty() {
+				let _ = self.connection.send(&alert, MessageFlag::DontWait.into()).await;
 			}
 
-			if flags.intersects(PollFlag::In) {
-				if self.tls.read_tls(&mut adapter)? == 0 {
-					eof = true;
-				} else if let Err(err) = self.tls.process_new_packets() {
-					/* we don't want to wait for writes in error state */
-					adapter.flags = MessageFlag::DontWait.into();
-
-					let _ = self.tls.write_tls(&mut adapter);
-
-					return Err(Error::new(err));
-				}
-			}
-
-			if handshaking && !self.tls.is_handshaking() && self.tls.wants_write() {
-				continue;
-			}
-
-			match (eof, handshaking, self.tls.is_handshaking()) {
-				(_, true, false) | (_, false, _) => break,
-				(true, true, true) => {
-					return Err(fmt_error!("EOF during TLS handshake" @ ErrorKind::UnexpectedEof))
-				}
-				(..) => ()
-			}
+			return Err(err);
 		}
 
 		let elapsed = now.elapsed();
@@ -188,102 +367,182 @@ impl TlsConn {
 		Ok(())
 	}
 
-	pub async fn connect_stats_config(
-		options: &ConnectOptions<'_>, config: Arc<ClientConfig>
+	/// Connects, sending `data` as TLS 1.3 early data if `config` has
+	/// [`ClientConfig::enable_early_data`] set and a session that allows it can
+	/// be resumed. Otherwise `data` is sent once the handshake completes. The
+	/// server may replay early data, so only use this for idempotent requests
+	pub async fn connect_stats_early_data(
+		options: &ConnectOptions<'_>, config: Arc<ClientConfig>, data: &[u8]
 	) -> Result<(Self, ConnectStats)> {
 		let server_name = options.host().to_string().try_into().map_err(Error::new)?;
-		let tls = ClientConnection::new(config, server_name).map_err(Error::new)?;
+		let mut tls = ClientConnection::new(config, server_name).map_err(Error::new)?;
+
+		/* only available when resuming a session that allows early data */
+		let early = match tls.early_data() {
+			Some(mut writer) if !data.is_empty() => io::Write::write(&mut writer, data)?,
+			_ => 0
+		};
 
 		let (connection, stats) = Conn::connect_stats(options).await?;
 
-		let mut connection = Self { connection, tls };
+		let mut connection = Self {
+			connection,
+			tls,
+			revocation: None,
+			allow_truncation: false,
+			close_notify_sent: false
+		};
 		let mut stats = stats.into();
+		let now = Instant::now();
+
+		if let Err(err) = connection.tls_connect(&mut stats).await {
+			emit(
+				options.listener(),
+				Event::TlsHandshake {
+					version: connection.protocol_version(),
+					cipher: connection.negotiated_cipher_suite().map(|suite| suite.suite()),
+					elapsed: now.elapsed(),
+					error: Some(&err)
+				}
+			);
+
+			return Err(err);
+		}
+
+		stats.resumed = connection.tls.handshake_kind() == Some(HandshakeKind::Resumed);
 
-		connection.tls_connect(&mut stats).await?;
+		stats.early_data = early != 0 && connection.tls.is_early_data_accepted();
+
+		emit(
+			options.listener(),
+			Event::TlsHandshake {
+				version: connection.protocol_version(),
+				cipher: connection.negotiated_cipher_suite().map(|suite| suite.suite()),
+				elapsed: stats.tls_connect,
+				error: None
+			}
+		);
+
+		/* rejected early data is discarded by the server and must be sent again */
+		let remaining = if stats.early_data { &data[early..] } else { data };
+
+		if !remaining.is_empty() {
+			trace!(
+				target: &connection,
+				"== Sending {} bytes after handshake, {} sent as early data",
+				remaining.len(),
+				data.len().saturating_sub(remaining.len())
+			);
+
+			connection.write_all(remaining).await?;
+		}
 
 		Ok((connection, stats))
 	}
 
+	pub async fn connect_stats_config(
+		options: &ConnectOptions<'_>, config: Arc<ClientConfig>
+	) -> Result<(Self, ConnectStats)> {
+		Self::connect_stats_early_data(options, config, &[]).await
+	}
+
 	pub async fn connect_config(
 		options: &ConnectOptions<'_>, config: Arc<ClientConfig>
 	) -> Result<Self> {
 		Ok(Self::connect_stats_config(options, config).await?.0)
 	}
 
-	pub async fn connect_stats(options: &ConnectOptions<'_>) -> Result<(Self, ConnectStats)> {
-		Self::connect_stats_config(options, get_tls_client_config().await).await
+	/// The parameters negotiated during the handshake
+	#[must_use]
+	pub fn info(&self) -> TlsInfo {
+		TlsInfo {
+			version: self.protocol_version(),
+			cipher: self.negotiated_cipher_suite().map(|suite| suite.suite()),
+			alpn: self.alpn_protocol().map(<[u8]>::to_vec),
+			peer_certificates: self.peer_certificates().map(<[_]>::to_vec).unwrap_or_default(),
+			revocation: self.revocation
+		}
 	}
 
-	pub async fn connect(options: &ConnectOptions<'_>) -> Result<Self> {
-		Ok(Self::connect_stats(options).await?.0)
+	/// The protocol agreed on with ALPN, if the server selected one of
+	/// [`ClientConfig::alpn_protocols`]
+	#[must_use]
+	pub fn alpn_protocol(&self) -> Option<&[u8]> {
+		self.tls.alpn_protocol()
 	}
 
-	async fn tls_read(
-		&mut self, mut read: impl FnMut(&mut ClientConnection) -> io::Result<usize>
-	) -> Result<usize> {
-		match read(&mut self.tls) {
-			Ok(0) => (),
-			Ok(n) => return Ok(n),
-			Err(err) if err.kind() == io::ErrorKind::WouldBlock => (),
-			Err(err) => return Err(err.into())
-		}
-
-		/* Safety: we are in an async function */
-		let mut adapter = unsafe { Adapter::new(&mut self.connection, get_context().await) };
+	#[must_use]
+	pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+		self.tls.protocol_version()
+	}
 
-		loop {
-			if self.tls.read_tls(&mut adapter)? == 0 {
-				return Ok(0);
-			}
+	#[must_use]
+	pub fn negotiated_cipher_suite(&self) -> Option<SupportedCipherSuite> {
+		self.tls.negotiated_cipher_suite()
+	}
 
-			let state = self.tls.process_new_packets().map_err(Error::new)?;
+	/// The certificate chain presented by the server, leaf first
+	#[must_use]
+	pub fn peer_certificates(&self) -> Option<&[CertificateDer<'static>]> {
+		self.tls.peer_certificates()
+	}
 
-			if state.plaintext_bytes_to_read() == 0 {
-				check_interrupt().await?;
+	/// Treat EOF without a close_notify as a clean close instead of failing
+	/// with [`TlsError::Truncated`], for servers known to skip it. Bodies
+	/// delimited by the connection closing can then be cut short unnoticed
+	pub fn set_allow_truncation(&mut self, allow: bool) {
+		self.allow_truncation = allow;
+	}
 
-				continue;
-			}
+	/// Sends a close_notify alert, telling the peer no more data follows.
+	/// Called by [`Self::shutdown`] and [`Self::close`]
+	pub async fn send_close_notify(&mut self) -> Result<()> {
+		send_close_notify(&mut self.connection, &mut self.tls, &mut self.close_notify_sent).await
+	}
 
-			break Ok(read(&mut self.tls)?);
+	pub async fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+		if how != Shutdown::Read {
+			self.send_close_notify().await?;
 		}
-	}
 
-	pub async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
-		self.tls_read(move |tls| io::Read::read(&mut tls.reader(), buf))
-			.await
+		self.connection.shutdown(how).await
 	}
 
-	async fn tls_write(
-		&mut self, write: impl Fn(&mut ClientConnection) -> io::Result<usize>
-	) -> Result<usize> {
-		/* Safety: we are in an async function */
-		let mut adapter = unsafe { Adapter::new(&mut self.connection, get_context().await) };
+	/// Sends a close_notify and closes the connection. The socket is closed
+	/// even if the alert can't be sent
+	pub async fn close(mut self) -> Result<()> {
+		let result = self.send_close_notify().await;
 
-		loop {
-			let wrote = write(&mut self.tls)?;
+		self.connection.close().await?;
 
-			while self.tls.wants_write() {
-				if self.tls.write_tls(&mut adapter)? == 0 {
-					return Ok(wrote);
-				}
+		result
+	}
 
-				check_interrupt_if_zero(wrote).await?;
-			}
+	pub async fn connect_stats(options: &ConnectOptions<'_>) -> Result<(Self, ConnectStats)> {
+		Self::connect_stats_config(options, get_tls_client_config().await).await
+	}
 
-			if wrote != 0 {
-				break Ok(wrote);
-			}
-		}
+	pub async fn connect(options: &ConnectOptions<'_>) -> Result<Self> {
+		Ok(Self::connect_stats(options).await?.0)
+	}
+
+	pub async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
+		let read = move |tls: &mut ClientConnection| io::Read::read(&mut tls.reader(), buf);
+
+		tls_read(&mut self.connection, &mut self.tls, self.allow_truncation, read).await
 	}
 
 	pub async fn send(&mut self, buf: &[u8]) -> Result<usize> {
-		self.tls_write(|tls| io::Write::write(&mut tls.writer(), buf))
-			.await
+		let write = |tls: &mut ClientConnection| io::Write::write(&mut tls.writer(), buf);
+
+		tls_write(&mut self.connection, &mut self.tls, write).await
 	}
 
 	pub async fn send_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
-		self.tls_write(|tls| io::Write::write_vectored(&mut tls.writer(), bufs))
-			.await
+		let write =
+			|tls: &mut ClientConnection| io::Write::write_vectored(&mut tls.writer(), bufs);
+
+		tls_write(&mut self.connection, &mut self.tls, write).await
 	}
 }
 
@@ -309,19 +568,102 @@ impl Write for TlsConn {
 	}
 }
 
-pub struct TlsReadHalf<'a> {
+/// The rustls state of a client or server connection, also shared by the
+/// halves of a split connection
+pub trait TlsSession: Send {
+	fn read_tls(&mut self, rd: &mut dyn io::Read) -> io::Result<usize>;
+
+	fn write_tls(&mut self, wr: &mut dyn io::Write) -> io::Result<usize>;
+
+	fn process_new_packets(&mut self) -> std::result::Result<IoState, rustls::Error>;
+
+	fn wants_write(&self) -> bool;
+
+	fn send_close_notify(&mut self);
+
+	fn reader(&mut self) -> rustls::Reader<'_>;
+
+	fn writer(&mut self) -> rustls::Writer<'_>;
+}
+
+macro_rules! impl_session {
+	($type:ty) => {
+		impl TlsSession for $type {
+			fn read_tls(&mut self, rd: &mut dyn io::Read) -> io::Result<usize> {
+				(**self).read_tls(rd)
+			}
+
+			fn write_tls(&mut self, wr: &mut dyn io::Write) -> io::Result<usize> {
+				(**self).write_tls(wr)
+			}
+
+			fn process_new_packets(&mut self) -> std::result::Result<IoState, rustls::Error> {
+				(**self).process_new_packets()
+			}
+
+			fn wants_write(&self) -> bool {
+				(**self).wants_write()
+			}
+
+			fn send_close_notify(&mut self) {
+				(**self).send_close_notify();
+			}
+
+			fn reader(&mut self) -> rustls::Reader<'_> {
+				(**self).reader()
+			}
+
+			fn writer(&mut self) -> rustls::Writer<'_> {
+				(**self).writer()
+			}
+		}
+	};
+}
+
+impl_session!(ClientConnection);
+impl_session!(ServerConnection);
+
+/// The rustls state of a split connection, shared by its halves
+pub(super) struct SplitTls<'a, S> {
+	session: &'a mut S,
+	close_notify_sent: &'a mut bool
+}
+
+impl<'a, S> SplitTls<'a, S> {
+	pub(super) fn new(session: &'a mut S, close_notify_sent: &'a mut bool) -> Arc<Mutex<Self>> {
+		Arc::new(Mutex::new(Self { session, close_notify_sent }))
+	}
+}
+
+/// Sends a close_notify on `connection`, unless either half or the connection
+/// before it was split already sent one. The lock is held until the alert is
+/// written, so it can't be interleaved with records from the other half
+#[asynchronous]
+async fn send_split_close_notify<S: TlsSession>(
+	connection: &mut SocketHalf<'_>, tls: &Mutex<SplitTls<'_, S>>
+) -> Result<()> {
+	let mut tls = tls.lock().await.unwrap();
+	let SplitTls { session, close_notify_sent } = &mut *tls;
+
+	send_close_notify(connection, *session, close_notify_sent).await
+}
+
+pub struct TlsReadHalf<'a, S = ClientConnection> {
 	connection: BufReader<SocketHalf<'a>>,
-	tls: Arc<Mutex<&'a mut ClientConnection>>
+	tls: Arc<Mutex<SplitTls<'a, S>>>,
+	allow_truncation: bool
 }
 
 #[asynchronous]
-impl<'a> TlsReadHalf<'a> {
-	fn new(connection: SocketHalf<'a>, tls: Arc<Mutex<&'a mut ClientConnection>>) -> Self {
-		Self { connection: BufReader::new(connection), tls }
+impl<'a, S: TlsSession> TlsReadHalf<'a, S> {
+	pub(super) fn new(
+		connection: SocketHalf<'a>, tls: Arc<Mutex<SplitTls<'a, S>>>, allow_truncation: bool
+	) -> Self {
+		Self { connection: BufReader::new(connection), tls, allow_truncation }
 	}
 
 	async fn tls_read(
-		&mut self, mut read: impl FnMut(&mut ClientConnection) -> io::Result<usize>
+		&mut self, mut read: impl FnMut(&mut S) -> io::Result<usize>
 	) -> Result<usize> {
 		struct Adapter<'a, 'b> {
 			connection: &'b mut BufReader<SocketHalf<'a>>
@@ -344,19 +686,19 @@ impl<'a> TlsReadHalf<'a> {
 		let mut tls = self.tls.lock().await.unwrap();
 
 		loop {
-			match read(&mut tls) {
+			match read(tls.session) {
 				Ok(0) => (),
 				Ok(n) => return Ok(n),
 				Err(err) if err.kind() == io::ErrorKind::WouldBlock => (),
-				Err(err) => return Err(err.into())
+				Err(err) => return check_eof(Err(err), self.allow_truncation)
 			}
 
 			if !self.connection.buffer().is_empty() {
 				let mut adapter = Adapter { connection: &mut self.connection };
 
-				tls.read_tls(&mut adapter)?;
+				tls.session.read_tls(&mut adapter)?;
 
-				let state = tls.process_new_packets().map_err(Error::new)?;
+				let state = tls.session.process_new_packets().map_err(Error::new)?;
 
 				if state.plaintext_bytes_to_read() != 0 {
 					continue;
@@ -365,9 +707,16 @@ impl<'a> TlsReadHalf<'a> {
 
 			drop(tls);
 
-			self.connection.fill().await?;
+			let filled = self.connection.fill().await?;
 
 			tls = self.tls.lock().await.unwrap();
+
+			if filled == 0 {
+				/* let rustls see the EOF, so the reader reports a missing close_notify */
+				tls.session.read_tls(&mut &[][..])?;
+
+				return check_eof(read(tls.session), self.allow_truncation);
+			}
 		}
 	}
 
@@ -375,44 +724,49 @@ impl<'a> TlsReadHalf<'a> {
 		self.connection.inner_mut().poll(flags).await
 	}
 
+	/// Sends a close_notify before shutting down the write side
 	pub async fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+		if how != Shutdown::Read {
+			send_split_close_notify(self.connection.inner_mut(), &self.tls).await?;
+		}
+
 		self.connection.inner_mut().shutdown(how).await
 	}
 }
 
 #[asynchronous]
-impl Read for TlsReadHalf<'_> {
+impl<S: TlsSession> Read for TlsReadHalf<'_, S> {
 	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
 		self.tls_read(|tls| io::Read::read(&mut tls.reader(), buf))
 			.await
 	}
 }
 
-pub struct TlsWriteHalf<'a> {
+pub struct TlsWriteHalf<'a, S = ClientConnection> {
 	connection: SocketHalf<'a>,
-	tls: Arc<Mutex<&'a mut ClientConnection>>
+	tls: Arc<Mutex<SplitTls<'a, S>>>
 }
 
 #[asynchronous]
-impl<'a> TlsWriteHalf<'a> {
-	fn new(connection: SocketHalf<'a>, tls: Arc<Mutex<&'a mut ClientConnection>>) -> Self {
+impl<'a, S: TlsSession> TlsWriteHalf<'a, S> {
+	pub(super) fn new(connection: SocketHalf<'a>, tls: Arc<Mutex<SplitTls<'a, S>>>) -> Self {
 		Self { connection, tls }
 	}
 
 	async fn tls_write(
-		&mut self, write: impl Fn(&mut ClientConnection) -> io::Result<usize>
+		&mut self, write: impl Fn(&mut S) -> io::Result<usize>
 	) -> Result<usize> {
 		loop {
 			let mut tls = self.tls.lock().await.unwrap();
 			let mut buf = UninitBuf::<DEFAULT_BUFFER_SIZE>::new();
 
-			let wrote = write(&mut tls)?;
+			let wrote = write(tls.session)?;
 
-			if !tls.wants_write() {
+			if !tls.session.wants_write() {
 				break Ok(wrote);
 			}
 
-			tls.write_tls(&mut buf)?;
+			tls.session.write_tls(&mut buf)?;
 
 			drop(tls);
 
@@ -430,13 +784,20 @@ impl<'a> TlsWriteHalf<'a> {
 		self.connection.poll(flags).await
 	}
 
+	/// Sends a close_notify before shutting down the write side
 	pub async fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+		if how != Shutdown::Read {
+			self.flush().await?;
+
+			send_split_close_notify(&mut self.connection, &self.tls).await?;
+		}
+
 		self.connection.shutdown(how).await
 	}
 }
 
 #[asynchronous]
-impl Write for TlsWriteHalf<'_> {
+impl<S: TlsSession> Write for TlsWriteHalf<'_, S> {
 	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
 		self.tls_write(|tls| io::Write::write(&mut tls.writer(), buf))
 			.await
@@ -447,11 +808,11 @@ impl Write for TlsWriteHalf<'_> {
 			let mut tls = self.tls.lock().await.unwrap();
 			let mut buf = UninitBuf::<DEFAULT_BUFFER_SIZE>::new();
 
-			if !tls.wants_write() {
+			if !tls.session.wants_write() {
 				break;
 			}
 
-			tls.write_tls(&mut buf)?;
+			tls.session.write_tls(&mut buf)?;
 
 			drop(tls);
 
@@ -477,10 +838,10 @@ impl SplitMut for TlsConn {
 
 	fn try_split(&mut self) -> Result<(Self::Reader<'_>, Self::Writer<'_>)> {
 		let conn = self.connection.try_split()?;
-		let tls = Arc::new(Mutex::new(&mut self.tls));
+		let tls = SplitTls::new(&mut self.tls, &mut self.close_notify_sent);
 
 		Ok((
-			TlsReadHalf::new(conn.0, tls.clone()),
+			TlsReadHalf::new(conn.0, tls.clone(), self.allow_truncation),
 			TlsWriteHalf::new(conn.1, tls)
 		))
 	}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::{Resumption, WebPkiServerVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+	CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme
+};
+use xx_core::warn;
+
+use super::*;
+
+/// A [`ServerCertVerifier`] that skips some or all certificate checks, for
+/// testing against self-signed or misconfigured servers. A warning is logged
+/// for every connection it accepts
+///
+/// Handshake signatures are still verified, so the server must hold the key
+/// of the certificate it presents
+#[derive(Debug)]
+pub struct DangerousVerifier {
+	inner: Arc<WebPkiServerVerifier>,
+	accept_invalid_certs: bool,
+	accept_invalid_hostnames: bool
+}
+
+impl DangerousVerifier {
+	/// With `accept_invalid_certs`, any certificate is accepted. With
+	/// `accept_invalid_hostnames`, the chain must be trusted by `roots` but
+	/// may be issued for any name
+	pub fn new(
+		roots: Arc<RootCertStore>, accept_invalid_certs: bool, accept_invalid_hostnames: bool
+	) -> Result<Self> {
+		let inner = WebPkiServerVerifier::builder(roots)
+			.build()
+			.map_err(Error::new)?;
+
+		Ok(Self { inner, accept_invalid_certs, accept_invalid_hostnames })
+	}
+
+	/// Copies `config`, replacing its verifier with a [`DangerousVerifier`]
+	/// trusting `roots`, which should be the roots `config` was built with.
+	/// Session resumption is disabled in the copy
+	pub fn install(
+		config: &ClientConfig, roots: Arc<RootCertStore>, accept_invalid_certs: bool,
+		accept_invalid_hostnames: bool
+	) -> Result<Arc<ClientConfig>> {
+		let verifier = Self::new(roots, accept_invalid_certs, accept_invalid_hostnames)?;
+		let mut config = config.clone();
+
+		config.dangerous().set_certificate_verifier(Arc::new(verifier));
+
+		/* sessions are shared with the original config, where a secure request
+		 * could resume a session that was never verified
+		 */
+		config.resumption = Resumption::disabled();
+
+		Ok(Arc::new(config))
+	}
+}
+
+impl ServerCertVerifier for DangerousVerifier {
+	fn verify_server_cert(
+		&self, end_entity: &CertificateDer<'_>, intermediates: &[CertificateDer<'_>],
+		server_name: &ServerName<'_>, ocsp_response: &[u8], now: UnixTime
+	) -> Result<ServerCertVerified, rustls::Error> {
+		if self.accept_invalid_certs {
+			warn!(
+				target: self,
+				"== Certificate verification disabled, accepting any certificate for {:?}",
+				server_name
+			);
+
+			return Ok(ServerCertVerified::assertion());
+		}
+
+		let result = self.inner.verify_server_cert(
+			end_entity,
+			intermediates,
+			server_name,
+			ocsp_response,
+			now
+		);
+
+		match result {
+			Err(rustls::Error::InvalidCertificate(CertificateError::NotValidForName))
+				if self.accept_invalid_hostnames =>
+			{
+				warn!(
+					target: self,
+					"== Hostname verification disabled, accepting certificate for {:?}",
+					server_name
+				);
+
+				Ok(ServerCertVerified::assertion())
+			}
+
+			result => {
+				if self.accept_invalid_hostnames {
+					warn!(target: self, "== Hostname verification disabled for {:?}", server_name);
+				}
+
+				result
+			}
+		}
+	}
+
+	fn verify_tls12_signature(
+		&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls12_signature(message, cert, dss)
+	}
+
+	fn verify_tls13_signature(
+		&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls13_signature(message, cert, dss)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.inner.supported_verify_schemes()
+	}
+}
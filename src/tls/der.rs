@@ -0,0 +1,181 @@
+use rustls::crypto::ring::default_provider;
+use x509_parser::prelude::*;
+
+const TAG_BIT_STRING: u8 = 0x03;
+pub(super) const TAG_SEQUENCE: u8 = 0x30;
+
+/// A minimal DER reader for the structures webpki doesn't parse for us
+pub(super) struct Der<'a>(pub(super) &'a [u8]);
+
+impl<'a> Der<'a> {
+	pub(super) fn peek(&self) -> Option<u8> {
+		self.0.first().copied()
+	}
+
+	/// Returns the tag, the contents and the whole encoding of the next
+	/// element
+	#[allow(clippy::arithmetic_side_effects)]
+	pub(super) fn next(&mut self) -> Option<(u8, &'a [u8], &'a [u8])> {
+		let data = self.0;
+		let tag = *data.first()?;
+		let first = *data.get(1)?;
+
+		let (len, header) = if first < 0x80 {
+			(first as usize, 2)
+		} else {
+			let count = (first & 0x7f) as usize;
+
+			if count == 0 || count > 4 {
+				return None;
+			}
+
+			let mut len = 0usize;
+
+			for byte in data.get(2..2 + count)? {
+				len = (len << 8) | *byte as usize;
+			}
+
+			(len, 2 + count)
+		};
+
+		let end = header.checked_add(len)?;
+		let element = (tag, data.get(header..end)?, data.get(..end)?);
+
+		self.0 = data.get(end..)?;
+
+		Some(element)
+	}
+
+	pub(super) fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+		match self.next()? {
+			(found, contents, _) if found == tag => Some(contents),
+			_ => None
+		}
+	}
+
+	pub(super) fn bit_string(&mut self) -> Option<&'a [u8]> {
+		match self.expect(TAG_BIT_STRING)?.split_first()? {
+			(0, bits) => Some(bits),
+			_ => None
+		}
+	}
+}
+
+/// Encodes `contents` as a SEQUENCE, restoring the outer tag webpki strips
+/// from trust anchor names and keys
+#[allow(clippy::arithmetic_side_effects, clippy::cast_possible_truncation)]
+pub(super) fn sequence(contents: &[u8]) -> Vec<u8> {
+	let mut data = vec![TAG_SEQUENCE];
+
+	if contents.len() < 0x80 {
+		data.push(contents.len() as u8);
+	} else {
+		let len = contents.len().to_be_bytes();
+		let skip = len.iter().take_while(|byte| **byte == 0).count();
+
+		data.push(0x80 | (len.len() - skip) as u8);
+		data.extend_from_slice(&len[skip..]);
+	}
+
+	data.extend_from_slice(contents);
+	data
+}
+
+/// Splits a SubjectPublicKeyInfo into its algorithm identifier contents and
+/// the key itself
+pub(super) fn spki_parts(spki: &[u8]) -> Option<(&[u8], &[u8])> {
+	let mut spki = Der(Der(spki).expect(TAG_SEQUENCE)?);
+
+	Some((spki.expect(TAG_SEQUENCE)?, spki.bit_string()?))
+}
+
+/// Splits a signed structure into the signed data, the signature algorithm
+/// identifier contents and the signature
+pub(super) fn signed_parts(data: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+	let mut signed = Der(Der(data).expect(TAG_SEQUENCE)?);
+	let (_, _, tbs) = signed.next()?;
+
+	Some((tbs, signed.expect(TAG_SEQUENCE)?, signed.bit_string()?))
+}
+
+pub(super) fn verify_signature(
+	spki: &[u8], algorithm: &[u8], message: &[u8], signature: &[u8]
+) -> bool {
+	let Some((key_algorithm, key)) = spki_parts(spki) else {
+		return false;
+	};
+
+	default_provider()
+		.signature_verification_algorithms
+		.all
+		.iter()
+		.filter(|alg| alg.public_key_alg_id().as_ref() == key_algorithm)
+		.filter(|alg| alg.signature_alg_id().as_ref() == algorithm)
+		.any(|alg| alg.verify_signature(key, message, signature).is_ok())
+}
+
+/// Whether `cert` names `issuer` as its issuer and is signed by the key in
+/// `issuer_spki`
+pub(super) fn is_issued_by(cert: &[u8], issuer: &[u8], issuer_spki: &[u8]) -> bool {
+	let Ok((_, parsed)) = X509Certificate::from_der(cert) else {
+		return false;
+	};
+
+	let Some((tbs, algorithm, signature)) = signed_parts(cert) else {
+		return false;
+	};
+
+	parsed.issuer().as_raw() == issuer && verify_signature(issuer_spki, algorithm, tbs, signature)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_der_reader() {
+		/* SEQUENCE { INTEGER 5, OCTET STRING "ab" } followed by NULL */
+		let data = [0x30, 0x07, 0x02, 0x01, 0x05, 0x04, 0x02, b'a', b'b', 0x05, 0x00];
+		let mut der = Der(&data);
+
+		assert_eq!(der.peek(), Some(TAG_SEQUENCE));
+
+		let mut inner = Der(der.expect(TAG_SEQUENCE).unwrap());
+
+		assert_eq!(inner.expect(0x02), Some(&[0x05][..]));
+		assert_eq!(inner.next(), Some((0x04, &b"ab"[..], &data[5..9])));
+		assert_eq!(inner.next(), None);
+		assert_eq!(der.expect(TAG_SEQUENCE), None);
+	}
+
+	#[test]
+	fn test_der_long_length() {
+		let mut data = vec![0x04, 0x81, 0x80];
+
+		data.extend([0; 0x80]);
+
+		assert_eq!(Der(&data).expect(0x04).map(<[u8]>::len), Some(0x80));
+
+		/* truncated contents, indefinite and oversized lengths */
+		assert_eq!(Der(&data[..100]).next(), None);
+		assert_eq!(Der(&[0x30, 0x80, 0x00, 0x00]).next(), None);
+		assert_eq!(Der(&[0x04, 0x85, 0x01, 0x00, 0x00, 0x00, 0x00]).next(), None);
+	}
+
+	#[test]
+	fn test_bit_string() {
+		assert_eq!(Der(&[0x03, 0x03, 0x00, 0x01, 0x02]).bit_string(), Some(&[1, 2][..]));
+		assert_eq!(Der(&[0x03, 0x02, 0x04, 0x10]).bit_string(), None);
+	}
+
+	#[test]
+	fn test_sequence() {
+		let short = [1; 0x7f];
+		let long = [2; 0x1234];
+
+		assert_eq!(&sequence(&short)[..2], [TAG_SEQUENCE, 0x7f]);
+		assert_eq!(&sequence(&long)[..4], [TAG_SEQUENCE, 0x82, 0x12, 0x34]);
+		assert_eq!(Der(&sequence(&long)).expect(TAG_SEQUENCE), Some(&long[..]));
+		assert_eq!(sequence(&[]), [TAG_SEQUENCE, 0]);
+	}
+}
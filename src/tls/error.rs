@@ -0,0 +1,38 @@
+use rustls::{CipherSuite, NamedGroup, ProtocolVersion};
+
+use super::*;
+
+#[errors]
+pub enum TlsError {
+	#[display("Unsupported TLS version {:?}", f0)]
+	#[kind = ErrorKind::InvalidInput]
+	UnsupportedVersion(ProtocolVersion),
+
+	#[display("Minimum TLS version {:?} is above the maximum {:?}", f0, f1)]
+	#[kind = ErrorKind::InvalidInput]
+	InvalidVersionRange(ProtocolVersion, ProtocolVersion),
+
+	#[display("Unsupported cipher suite {:?}", f0)]
+	#[kind = ErrorKind::InvalidInput]
+	UnsupportedCipherSuite(CipherSuite),
+
+	#[display("None of the allowed cipher suites can be used with the allowed TLS versions")]
+	#[kind = ErrorKind::InvalidInput]
+	NoCipherSuites,
+
+	#[display("Unsupported key exchange group {:?}", f0)]
+	#[kind = ErrorKind::InvalidInput]
+	UnsupportedKeyExchangeGroup(NamedGroup),
+
+	#[display("No key exchange groups allowed")]
+	#[kind = ErrorKind::InvalidInput]
+	NoKeyExchangeGroups,
+
+	#[display("The shared default TLS config is already in use")]
+	#[kind = ErrorKind::InvalidInput]
+	DefaultConfigInUse,
+
+	#[display("Connection closed without a TLS close_notify, data may be truncated")]
+	#[kind = ErrorKind::UnexpectedEof]
+	Truncated
+}
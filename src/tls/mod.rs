@@ -1,4 +1,12 @@
 use super::*;
 
 pub mod certs;
+pub mod config;
 pub mod conn;
+pub mod danger;
+mod der;
+pub mod error;
+pub mod pin;
+pub mod policy;
+pub mod revocation;
+pub mod server;
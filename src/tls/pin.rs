@@ -0,0 +1,326 @@
+use std::sync::Arc;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use x509_parser::prelude::*;
+use xx_core::warn;
+
+use super::*;
+use crate::tls::der::{is_issued_by, sequence};
+
+const SHA256_LEN: usize = 32;
+
+type Sha256Hash = [u8; SHA256_LEN];
+
+pub(crate) fn sha256(data: &[u8]) -> Sha256Hash {
+	let mut hasher = Sha256::new();
+	let mut hash = [0; SHA256_LEN];
+
+	hasher.input(data);
+	hasher.result(&mut hash);
+	hash
+}
+
+/// The SHA-256 hash of a certificate's DER encoded SubjectPublicKeyInfo
+pub fn spki_sha256(cert: &CertificateDer<'_>) -> Result<[u8; 32]> {
+	let (_, cert) = X509Certificate::from_der(cert).map_err(Error::new)?;
+
+	Ok(sha256(cert.public_key().raw))
+}
+
+/// A set of pins, one of which must match the server's certificates
+#[derive(Default, Clone, Debug)]
+pub struct PinSet {
+	spki: Vec<Sha256Hash>,
+	leaf: Vec<Sha256Hash>
+}
+
+impl PinSet {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Pin the SHA-256 hash of a SubjectPublicKeyInfo. Matches any certificate
+	/// on the validated path, including the root, so pinning a CA's key is
+	/// possible. Without chain validation, only the leaf's key is checked
+	pub fn spki_sha256(&mut self, hash: [u8; 32]) -> &mut Self {
+		self.spki.push(hash);
+		self
+	}
+
+	/// Pin a base64 encoded SPKI SHA-256 hash, as used by HPKP's
+	/// `pin-sha256`. An optional `sha256/` prefix is accepted
+	pub fn spki_sha256_base64(&mut self, pin: &str) -> Result<&mut Self> {
+		let pin = pin.strip_prefix("sha256/").unwrap_or(pin);
+		let hash = STANDARD
+			.decode(pin)
+			.ok()
+			.and_then(|hash| Sha256Hash::try_from(hash).ok())
+			.ok_or_else(|| fmt_error!("Invalid SPKI pin" @ ErrorKind::InvalidInput))?;
+
+		Ok(self.spki_sha256(hash))
+	}
+
+	/// Pin the exact leaf certificate the server must present
+	pub fn certificate(&mut self, cert: &CertificateDer<'_>) -> &mut Self {
+		self.leaf.push(sha256(cert));
+		self
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.spki.is_empty() && self.leaf.is_empty()
+	}
+
+	/// Whether `end_entity` or a key on `path`, as returned by
+	/// [`verified_path`], matches a pin
+	fn matches(&self, end_entity: &CertificateDer<'_>, path: &[Sha256Hash]) -> bool {
+		self.leaf.contains(&sha256(end_entity)) || path.iter().any(|hash| self.spki.contains(hash))
+	}
+}
+
+/// The SPKI hashes of the certificates on the path from `end_entity` to a
+/// trust anchor in `roots`, following only issuers whose signatures verify.
+/// Certificates the server sent that aren't on the path, such as an appended
+/// copy of a pinned certificate, are ignored
+fn verified_path(
+	end_entity: &CertificateDer<'_>, intermediates: &[CertificateDer<'_>], roots: &RootCertStore
+) -> Vec<Sha256Hash> {
+	let mut path = Vec::new();
+	let mut remaining: Vec<_> = intermediates.iter().collect();
+	let mut cert: &[u8] = end_entity;
+
+	/* unparsable certificates can't match a pin */
+	while let Ok((_, parsed)) = X509Certificate::from_der(cert) {
+		path.push(sha256(parsed.public_key().raw));
+
+		let issuer = remaining.iter().position(|issuer| {
+			X509Certificate::from_der(issuer).is_ok_and(|(_, issuer)| {
+				is_issued_by(cert, issuer.subject().as_raw(), issuer.public_key().raw)
+			})
+		});
+
+		if let Some(index) = issuer {
+			cert = &remaining.swap_remove(index)[..];
+
+			continue;
+		}
+
+		let anchor = roots
+			.roots
+			.iter()
+			.map(|anchor| (sequence(&anchor.subject), sequence(&anchor.subject_public_key_info)))
+			.find(|(subject, spki)| is_issued_by(cert, subject, spki));
+
+		if let Some((_, spki)) = anchor {
+			path.push(sha256(&spki));
+		}
+
+		break;
+	}
+
+	path
+}
+
+/// Whether pins are checked in addition to or instead of chain validation
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PinMode {
+	/// The chain must be trusted by the root store and match a pin
+	#[default]
+	WithChainValidation,
+
+	/// Only a pin has to match. The chain and hostname are not validated, so
+	/// this should only be used with leaf or self-signed certificate pins
+	WithoutChainValidation
+}
+
+/// A [`ServerCertVerifier`] that rejects servers whose certificates match none
+/// of the pins in a [`PinSet`]. Install with
+/// `ClientConfig::dangerous().set_certificate_verifier` for
+/// [`TlsConn::connect_config`], or use `TlsConfig::pin`
+///
+/// [`TlsConn::connect_config`]: super::conn::TlsConn::connect_config
+#[derive(Debug)]
+pub struct PinnedVerifier {
+	inner: Arc<dyn ServerCertVerifier>,
+	roots: Arc<RootCertStore>,
+	pins: PinSet,
+	mode: PinMode
+}
+
+impl PinnedVerifier {
+	pub fn new(pins: PinSet, mode: PinMode, roots: Arc<RootCertStore>) -> Result<Self> {
+		let inner = WebPkiServerVerifier::builder(roots.clone())
+			.build()
+			.map_err(Error::new)?;
+
+		Self::with_verifier(pins, mode, roots, inner)
+	}
+
+	/// Check pins after `inner` accepts the chain, such as a
+	/// [`RevocationVerifier`]. `inner` must validate the chain against
+	/// `roots`, which are used to find the pinnable path
+	///
+	/// [`RevocationVerifier`]: super::revocation::RevocationVerifier
+	pub fn with_verifier(
+		pins: PinSet, mode: PinMode, roots: Arc<RootCertStore>, inner: Arc<dyn ServerCertVerifier>
+	) -> Result<Self> {
+		if pins.is_empty() {
+			return Err(fmt_error!("Pin set is empty" @ ErrorKind::InvalidInput));
+		}
+
+		Ok(Self { inner, roots, pins, mode })
+	}
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+	fn verify_server_cert(
+		&self, end_entity: &CertificateDer<'_>, intermediates: &[CertificateDer<'_>],
+		server_name: &ServerName<'_>, ocsp_response: &[u8], now: UnixTime
+	) -> Result<ServerCertVerified, rustls::Error> {
+		if self.mode == PinMode::WithChainValidation {
+			self.inner.verify_server_cert(
+				end_entity,
+				intermediates,
+				server_name,
+				ocsp_response,
+				now
+			)?;
+		}
+
+		let path = match self.mode {
+			PinMode::WithChainValidation => verified_path(end_entity, intermediates, &self.roots),
+			PinMode::WithoutChainValidation => spki_sha256(end_entity).into_iter().collect()
+		};
+
+		if !self.pins.matches(end_entity, &path) {
+			warn!(target: self, "== Certificate for {:?} does not match any pin", server_name);
+
+			return Err(rustls::Error::InvalidCertificate(
+				CertificateError::ApplicationVerificationFailure
+			));
+		}
+
+		Ok(ServerCertVerified::assertion())
+	}
+
+	fn verify_tls12_signature(
+		&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls12_signature(message, cert, dss)
+	}
+
+	fn verify_tls13_signature(
+		&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls13_signature(message, cert, dss)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.inner.supported_verify_schemes()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tls::certs::parse_pem_certs;
+
+	const CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBfTCCASOgAwIBAgIUKiIV6Ylj7ZvARFCWTOTEaN8LAc4wCgYIKoZIzj0EAwIw
+EzERMA8GA1UEAwwIcGluLnRlc3QwIBcNMjYxMDE4MTUxMzMxWhgPMjEyNjA5MjQx
+NTEzMzFaMBMxETAPBgNVBAMMCHBpbi50ZXN0MFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAEesaUX7a/74nwFym/e72uTs66uYVJVyfx45I8OpfWb8t7vvxow++stBLp
+IC1JiMYkRZ6p2Et4zEq399++8b8vfqNTMFEwHQYDVR0OBBYEFJS7QRNEKqHh4MUP
+36Y8k8rK0GFLMB8GA1UdIwQYMBaAFJS7QRNEKqHh4MUP36Y8k8rK0GFLMA8GA1Ud
+EwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIhAI+qFkGoyMjJYhF0NL8ax+GL
+VvVGmvRjPDyim8C2VHO5AiBJUShTEVa8dzTgJh3a/W9Uqkopu7JFyM+OIYVFM70d
+oA==
+-----END CERTIFICATE-----
+";
+
+	/* openssl x509 -pubkey -noout | openssl pkey -pubin -outform der | sha256 */
+	const SPKI_PIN: &str = "W/SDwXtKMw0ON22gD1CdU/jnDNO0Yb+8KSKj8DaIpAs=";
+
+	fn cert() -> CertificateDer<'static> {
+		parse_pem_certs(CERT.as_bytes()).unwrap().remove(0)
+	}
+
+	#[test]
+	fn test_spki_sha256_base64() {
+		let mut pins = PinSet::new();
+
+		pins.spki_sha256_base64(SPKI_PIN).unwrap();
+		pins.spki_sha256_base64(&format!("sha256/{}", SPKI_PIN)).unwrap();
+
+		assert_eq!(pins.spki, [spki_sha256(&cert()).unwrap(); 2]);
+
+		assert!(pins.spki_sha256_base64("not base64!").is_err());
+		assert!(pins.spki_sha256_base64("AAAA").is_err());
+	}
+
+	#[test]
+	fn test_spki_pin_matches() {
+		let cert = cert();
+		let other = CertificateDer::from(b"not a certificate".to_vec());
+		let roots = RootCertStore::empty();
+		let mut pins = PinSet::new();
+
+		pins.spki_sha256_base64(SPKI_PIN).unwrap();
+
+		assert!(pins.matches(&cert, &verified_path(&cert, &[], &roots)));
+
+		/* a copy of the pinned certificate that didn't issue the leaf */
+		let path = verified_path(&other, &[cert.clone()], &roots);
+
+		assert!(path.is_empty());
+		assert!(!pins.matches(&other, &path));
+
+		let mut pins = PinSet::new();
+
+		pins.spki_sha256([0; 32]);
+
+		assert!(!pins.matches(&cert, &verified_path(&cert, &[], &roots)));
+	}
+
+	#[test]
+	fn test_verified_path() {
+		let cert = cert();
+		let hash = spki_sha256(&cert).unwrap();
+		let mut roots = RootCertStore::empty();
+
+		assert_eq!(verified_path(&cert, &[], &roots), [hash]);
+
+		/* self-signed, so it issued itself */
+		assert_eq!(verified_path(&cert, &[cert.clone()], &roots), [hash; 2]);
+
+		roots.add(cert.clone()).unwrap();
+
+		assert_eq!(verified_path(&cert, &[], &roots), [hash; 2]);
+	}
+
+	#[test]
+	fn test_leaf_pin_matches() {
+		let cert = cert();
+		let other = CertificateDer::from(b"not a certificate".to_vec());
+		let roots = RootCertStore::empty();
+		let mut pins = PinSet::new();
+
+		assert!(pins.is_empty());
+
+		pins.certificate(&other);
+
+		assert!(!pins.is_empty());
+		assert!(pins.matches(&other, &[]));
+
+		/* leaf pins don't match intermediates */
+		assert!(!pins.matches(&cert, &verified_path(&cert, &[other], &roots)));
+	}
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use rustls::crypto::ring::default_provider;
+use rustls::crypto::CryptoProvider;
+use rustls::version::{TLS12, TLS13};
+use rustls::{
+	CipherSuite, ClientConfig, ConfigBuilder, NamedGroup, ProtocolVersion, ServerConfig,
+	SupportedProtocolVersion, WantsVerifier
+};
+
+use super::*;
+use crate::tls::error::TlsError;
+
+const VERSIONS: [&SupportedProtocolVersion; 2] = [&TLS12, &TLS13];
+
+/// Restricts the protocol versions, cipher suites and key exchange groups a
+/// TLS connection may negotiate. Everything supported is allowed by default
+#[derive(Default, Clone, Debug)]
+pub struct TlsPolicy {
+	min_version: Option<ProtocolVersion>,
+	max_version: Option<ProtocolVersion>,
+	cipher_suites: Option<Vec<CipherSuite>>,
+	kx_groups: Option<Vec<NamedGroup>>
+}
+
+fn version_index(version: ProtocolVersion) -> Result<usize> {
+	VERSIONS
+		.iter()
+		.position(|supported| supported.version == version)
+		.ok_or_else(|| TlsError::UnsupportedVersion(version).into())
+}
+
+impl TlsPolicy {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Only TLS 1.2 and TLS 1.3 are supported
+	pub fn min_version(&mut self, version: ProtocolVersion) -> &mut Self {
+		self.min_version = Some(version);
+		self
+	}
+
+	pub fn max_version(&mut self, version: ProtocolVersion) -> &mut Self {
+		self.max_version = Some(version);
+		self
+	}
+
+	/// Only allow `suites`, which must include suites for each allowed TLS
+	/// version that should remain usable
+	pub fn cipher_suites(&mut self, suites: &[CipherSuite]) -> &mut Self {
+		self.cipher_suites = Some(suites.to_vec());
+		self
+	}
+
+	/// Only allow `groups` for (EC)DHE key exchange, in order of preference
+	pub fn key_exchange_groups(&mut self, groups: &[NamedGroup]) -> &mut Self {
+		self.kx_groups = Some(groups.to_vec());
+		self
+	}
+
+	/// Checks that the policy can be satisfied, returning the crypto provider
+	/// and protocol versions to build configs with
+	pub(crate) fn resolve(
+		&self
+	) -> Result<(Arc<CryptoProvider>, Vec<&'static SupportedProtocolVersion>)> {
+		let mut provider = default_provider();
+
+		let min = self.min_version.map(version_index).transpose()?.unwrap_or(0);
+		let max = self
+			.max_version
+			.map(version_index)
+			.transpose()?
+			.unwrap_or(VERSIONS.len().saturating_sub(1));
+
+		if min > max {
+			return Err(TlsError::InvalidVersionRange(
+				VERSIONS[min].version,
+				VERSIONS[max].version
+			)
+			.into());
+		}
+
+		let versions = &VERSIONS[min..=max];
+
+		if let Some(allowed) = &self.cipher_suites {
+			for suite in allowed {
+				if !provider.cipher_suites.iter().any(|supported| supported.suite() == *suite) {
+					return Err(TlsError::UnsupportedCipherSuite(*suite).into());
+				}
+			}
+
+			provider
+				.cipher_suites
+				.retain(|supported| allowed.contains(&supported.suite()));
+		}
+
+		provider.cipher_suites.retain(|suite| {
+			versions
+				.iter()
+				.any(|version| version.version == suite.version().version)
+		});
+
+		if let Some(allowed) = &self.kx_groups {
+			let mut groups = Vec::new();
+
+			for group in allowed {
+				match provider.kx_groups.iter().find(|supported| supported.name() == *group) {
+					Some(supported) => groups.push(*supported),
+					None => return Err(TlsError::UnsupportedKeyExchangeGroup(*group).into())
+				}
+			}
+
+			provider.kx_groups = groups;
+		}
+
+		if provider.kx_groups.is_empty() {
+			return Err(TlsError::NoKeyExchangeGroups.into());
+		}
+
+		/* drop versions left without any cipher suite */
+		let versions: Vec<_> = versions
+			.iter()
+			.copied()
+			.filter(|version| {
+				provider
+					.cipher_suites
+					.iter()
+					.any(|suite| suite.version().version == version.version)
+			})
+			.collect();
+
+		if versions.is_empty() {
+			return Err(TlsError::NoCipherSuites.into());
+		}
+
+		Ok((Arc::new(provider), versions))
+	}
+
+	pub(crate) fn client_builder(&self) -> Result<ConfigBuilder<ClientConfig, WantsVerifier>> {
+		let (provider, versions) = self.resolve()?;
+
+		ClientConfig::builder_with_provider(provider)
+			.with_protocol_versions(&versions)
+			.map_err(Error::new)
+	}
+
+	pub(crate) fn server_builder(&self) -> Result<ConfigBuilder<ServerConfig, WantsVerifier>> {
+		let (provider, versions) = self.resolve()?;
+
+		ServerConfig::builder_with_provider(provider)
+			.with_protocol_versions(&versions)
+			.map_err(Error::new)
+	}
+}
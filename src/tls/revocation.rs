@@ -0,0 +1,577 @@
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, CertificateRevocationListDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use rustls_pemfile::crls;
+use x509_parser::prelude::*;
+use xx_core::warn;
+use xx_pulse::*;
+
+use super::*;
+use crate::tls::der::{
+	is_issued_by, signed_parts, spki_parts, verify_signature, Der, TAG_SEQUENCE
+};
+use crate::tls::pin::sha256;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_ENUMERATED: u8 = 0x0a;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_CONTEXT_0: u8 = 0xa0;
+const TAG_CONTEXT_1: u8 = 0xa1;
+const TAG_CONTEXT_2: u8 = 0xa2;
+
+/* CertStatus choices, [0] and [2] are IMPLICIT NULL */
+const TAG_GOOD: u8 = 0x80;
+const TAG_REVOKED: u8 = 0xa1;
+const TAG_UNKNOWN: u8 = 0x82;
+
+/* 1.3.6.1.5.5.7.48.1.1 */
+const OID_OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+
+/* 1.3.14.3.2.26 */
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+
+/* 2.16.840.1.101.3.4.2.1 */
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+
+/// Allowed difference between our clock and the OCSP responder's
+const CLOCK_SKEW_SECS: u64 = 5 * 60;
+
+/// Whether the server's certificate has been revoked
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RevocationStatus {
+	/// A stapled OCSP response or a loaded CRL shows the certificate is valid
+	Good,
+
+	/// The certificate was revoked by its issuer
+	Revoked,
+
+	/// Neither a valid OCSP response nor a CRL for the issuer was available
+	Unknown
+}
+
+/// What to do when the revocation status can't be determined
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RevocationPolicy {
+	/// Accept the certificate
+	#[default]
+	SoftFail,
+
+	/// Reject the certificate
+	HardFail
+}
+
+/// Converts a `YYYYMMDDHHMMSS[.fff]Z` GeneralizedTime to seconds since the
+/// epoch
+#[allow(clippy::arithmetic_side_effects, clippy::cast_sign_loss)]
+fn parse_time(time: &[u8]) -> Option<u64> {
+	if time.len() < 15 || time.last() != Some(&b'Z') {
+		return None;
+	}
+
+	let mut fields = [0i64; 6];
+	let widths = [4, 2, 2, 2, 2, 2];
+	let mut pos = 0;
+
+	for (field, width) in fields.iter_mut().zip(widths) {
+		for digit in time.get(pos..pos + width)? {
+			if !digit.is_ascii_digit() {
+				return None;
+			}
+
+			*field = *field * 10 + i64::from(digit - b'0');
+		}
+
+		pos += width;
+	}
+
+	let [year, month, day, hour, minute, second] = fields;
+
+	/* days from civil, see http://howardhinnant.github.io/date_algorithms.html */
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	let days = era * 146_097 + day_of_era - 719_468;
+	let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+
+	(secs >= 0).then_some(secs as u64)
+}
+
+struct SingleResponse<'a> {
+	hash_algorithm: &'a [u8],
+	name_hash: &'a [u8],
+	key_hash: &'a [u8],
+	serial: &'a [u8],
+	status: RevocationStatus,
+	this_update: u64,
+	next_update: Option<u64>
+}
+
+impl<'a> SingleResponse<'a> {
+	fn parse(data: &'a [u8]) -> Option<Self> {
+		let mut single = Der(data);
+		let mut cert_id = Der(single.expect(TAG_SEQUENCE)?);
+		let hash_algorithm = Der(cert_id.expect(TAG_SEQUENCE)?).expect(TAG_OID)?;
+		let name_hash = cert_id.expect(TAG_OCTET_STRING)?;
+		let key_hash = cert_id.expect(TAG_OCTET_STRING)?;
+		let serial = cert_id.expect(TAG_INTEGER)?;
+
+		let status = match single.next()?.0 {
+			TAG_GOOD => RevocationStatus::Good,
+			TAG_REVOKED => RevocationStatus::Revoked,
+			TAG_UNKNOWN => RevocationStatus::Unknown,
+			_ => return None
+		};
+
+		let this_update = parse_time(single.expect(TAG_GENERALIZED_TIME)?)?;
+		let next_update = if single.peek() == Some(TAG_CONTEXT_0) {
+			let time = Der(single.expect(TAG_CONTEXT_0)?).expect(TAG_GENERALIZED_TIME)?;
+
+			Some(parse_time(time)?)
+		} else {
+			None
+		};
+
+		Some(Self {
+			hash_algorithm,
+			name_hash,
+			key_hash,
+			serial,
+			status,
+			this_update,
+			next_update
+		})
+	}
+
+	fn matches(&self, cert: &X509Certificate<'_>, issuer: &X509Certificate<'_>) -> bool {
+		let Some((_, issuer_key)) = spki_parts(issuer.public_key().raw) else {
+			return false;
+		};
+
+		let hash = |data: &[u8]| match self.hash_algorithm {
+			OID_SHA1 => {
+				let mut hasher = Sha1::new();
+				let mut hash = vec![0; hasher.output_bytes()];
+
+				hasher.input(data);
+				hasher.result(&mut hash);
+
+				Some(hash)
+			}
+
+			OID_SHA256 => Some(sha256(data).to_vec()),
+			_ => None
+		};
+
+		self.serial == cert.raw_serial() &&
+			hash(issuer.subject().as_raw()).is_some_and(|hash| hash == self.name_hash) &&
+			hash(issuer_key).is_some_and(|hash| hash == self.key_hash)
+	}
+}
+
+/// Checks that `responder` may sign OCSP responses on behalf of `issuer`
+fn is_delegated_responder(responder: &[u8], issuer: &X509Certificate<'_>) -> bool {
+	let Ok((_, cert)) = X509Certificate::from_der(responder) else {
+		return false;
+	};
+
+	cert.validity().is_valid() &&
+		matches!(cert.extended_key_usage(), Ok(Some(eku)) if eku.value.ocsp_signing) &&
+		is_issued_by(responder, issuer.subject().as_raw(), issuer.public_key().raw)
+}
+
+/// Validates a stapled OCSP response for `cert`, returning its status, or
+/// `None` if the response is malformed, expired, improperly signed or
+/// doesn't cover the certificate
+fn check_ocsp(
+	cert: &X509Certificate<'_>, issuer: &X509Certificate<'_>, response: &[u8], now: u64
+) -> Option<RevocationStatus> {
+	let mut outer = Der(Der(response).expect(TAG_SEQUENCE)?);
+
+	/* successful */
+	if outer.expect(TAG_ENUMERATED)? != [0] {
+		return None;
+	}
+
+	let mut bytes = Der(Der(outer.expect(TAG_CONTEXT_0)?).expect(TAG_SEQUENCE)?);
+
+	if bytes.expect(TAG_OID)? != OID_OCSP_BASIC {
+		return None;
+	}
+
+	let basic = bytes.expect(TAG_OCTET_STRING)?;
+	let (tbs, algorithm, signature) = signed_parts(basic)?;
+
+	let mut certs = Vec::new();
+	let mut rest = Der(Der(basic).expect(TAG_SEQUENCE)?);
+
+	/* skip tbsResponseData, signatureAlgorithm and signature */
+	for _ in 0..3 {
+		rest.next()?;
+	}
+
+	if rest.peek() == Some(TAG_CONTEXT_0) {
+		let mut list = Der(Der(rest.expect(TAG_CONTEXT_0)?).expect(TAG_SEQUENCE)?);
+
+		while let Some((TAG_SEQUENCE, _, cert)) = list.next() {
+			certs.push(cert);
+		}
+	}
+
+	let signed_by_issuer = verify_signature(issuer.public_key().raw, algorithm, tbs, signature);
+	let signed_by_delegate = || {
+		certs.iter().any(|responder| {
+			is_delegated_responder(responder, issuer) &&
+				X509Certificate::from_der(responder).is_ok_and(|(_, responder)| {
+					verify_signature(responder.public_key().raw, algorithm, tbs, signature)
+				})
+		})
+	};
+
+	if !signed_by_issuer && !signed_by_delegate() {
+		return None;
+	}
+
+	let mut data = Der(Der(tbs).expect(TAG_SEQUENCE)?);
+
+	/* version */
+	if data.peek() == Some(TAG_CONTEXT_0) {
+		data.next()?;
+	}
+
+	/* responderID, implied by the signature check above */
+	match data.next()?.0 {
+		TAG_CONTEXT_1 | TAG_CONTEXT_2 => (),
+		_ => return None
+	}
+
+	data.expect(TAG_GENERALIZED_TIME)?;
+
+	let mut responses = Der(data.expect(TAG_SEQUENCE)?);
+
+	while let Some((TAG_SEQUENCE, single, _)) = responses.next() {
+		let single = SingleResponse::parse(single)?;
+
+		if !single.matches(cert, issuer) {
+			continue;
+		}
+
+		if single.this_update > now.saturating_add(CLOCK_SKEW_SECS) ||
+			single
+				.next_update
+				.is_some_and(|next| next.saturating_add(CLOCK_SKEW_SECS) < now)
+		{
+			return None;
+		}
+
+		return Some(single.status);
+	}
+
+	None
+}
+
+thread_local! {
+	/// The result of the last verification on this thread. Verifiers run
+	/// synchronously within `process_new_packets`, so a connection taking it
+	/// right after gets the result of its own handshake
+	static STATUS: Cell<Option<RevocationStatus>> = const { Cell::new(None) };
+}
+
+fn record_status(status: RevocationStatus) {
+	STATUS.set(Some(status));
+}
+
+/// Takes the result recorded by a [`RevocationVerifier`] on this thread
+pub(crate) fn take_status() -> Option<RevocationStatus> {
+	STATUS.take()
+}
+
+/// Reads CRLs from a PEM or DER file
+#[asynchronous]
+pub async fn load_crls(
+	path: impl AsRef<Path>
+) -> Result<Vec<CertificateRevocationListDer<'static>>> {
+	let data = fs::read(path).await?;
+
+	if data.starts_with(b"-----") {
+		crls(&mut &data[..])
+			.map(|result| result.map_err(Into::into))
+			.collect()
+	} else {
+		Ok(vec![data.into()])
+	}
+}
+
+/// Configures revocation checking for `TlsConfig::revocation`
+#[derive(Clone, Debug)]
+pub struct RevocationConfig {
+	crls: Vec<CertificateRevocationListDer<'static>>,
+	crl_files: Vec<PathBuf>,
+	ocsp: bool,
+	policy: RevocationPolicy
+}
+
+impl Default for RevocationConfig {
+	fn default() -> Self {
+		Self {
+			crls: Vec::new(),
+			crl_files: Vec::new(),
+			ocsp: true,
+			policy: RevocationPolicy::default()
+		}
+	}
+}
+
+#[asynchronous]
+impl RevocationConfig {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn add_crl(&mut self, crl: CertificateRevocationListDer<'static>) -> &mut Self {
+		self.crls.push(crl);
+		self
+	}
+
+	/// Read CRLs from the PEM or DER file at `path` when the config is built
+	#[allow(clippy::impl_trait_in_params)]
+	pub fn add_crl_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+		self.crl_files.push(path.into());
+		self
+	}
+
+	/// Check OCSP responses stapled by the server. Enabled by default
+	pub fn check_ocsp(&mut self, check: bool) -> &mut Self {
+		self.ocsp = check;
+		self
+	}
+
+	pub fn policy(&mut self, policy: RevocationPolicy) -> &mut Self {
+		self.policy = policy;
+		self
+	}
+
+	pub(crate) async fn verifier(&self, roots: Arc<RootCertStore>) -> Result<RevocationVerifier> {
+		let mut crls = self.crls.clone();
+
+		for path in &self.crl_files {
+			crls.append(&mut load_crls(path).await?);
+		}
+
+		RevocationVerifier::new(roots, crls, self.ocsp, self.policy)
+	}
+}
+
+/// The issuer and validity period of a loaded CRL
+#[derive(Debug)]
+struct CrlIssuer {
+	name: Vec<u8>,
+	this_update: i64,
+	next_update: Option<i64>
+}
+
+impl CrlIssuer {
+	/// Whether the CRL was issued by `name` and is current at `now`
+	#[allow(clippy::cast_possible_wrap)]
+	fn covers(&self, name: &[u8], now: u64) -> bool {
+		let now = now as i64;
+		let skew = CLOCK_SKEW_SECS as i64;
+
+		self.name == name &&
+			self.this_update <= now.saturating_add(skew) &&
+			!self
+				.next_update
+				.is_some_and(|next| next.saturating_add(skew) < now)
+	}
+}
+
+/// A [`ServerCertVerifier`] that checks the server certificate against CRLs
+/// and stapled OCSP responses after validating the chain. The result is
+/// reported in [`TlsInfo::revocation`], except for resumed sessions, which
+/// skip verification
+///
+/// [`TlsInfo::revocation`]: super::conn::TlsInfo::revocation
+#[derive(Debug)]
+pub struct RevocationVerifier {
+	inner: Arc<WebPkiServerVerifier>,
+	crl_issuers: Vec<CrlIssuer>,
+	ocsp: bool,
+	policy: RevocationPolicy
+}
+
+impl RevocationVerifier {
+	pub fn new(
+		roots: Arc<RootCertStore>, crls: Vec<CertificateRevocationListDer<'static>>, ocsp: bool,
+		policy: RevocationPolicy
+	) -> Result<Self> {
+		let mut crl_issuers = Vec::new();
+
+		for crl in &crls {
+			let (_, crl) = CertificateRevocationList::from_der(crl).map_err(Error::new)?;
+
+			crl_issuers.push(CrlIssuer {
+				name: crl.issuer().as_raw().to_vec(),
+				this_update: crl.last_update().timestamp(),
+				next_update: crl.next_update().map(|time| time.timestamp())
+			});
+		}
+
+		let mut builder = WebPkiServerVerifier::builder(roots);
+
+		if !crls.is_empty() {
+			/* a missing CRL is handled by our policy */
+			builder = builder
+				.with_crls(crls)
+				.only_check_end_entity_revocation()
+				.allow_unknown_revocation_status();
+		}
+
+		let inner = builder.build().map_err(Error::new)?;
+
+		Ok(Self { inner, crl_issuers, ocsp, policy })
+	}
+
+	fn status(
+		&self, end_entity: &CertificateDer<'_>, intermediates: &[CertificateDer<'_>],
+		ocsp_response: &[u8], now: UnixTime
+	) -> RevocationStatus {
+		let Ok((_, cert)) = X509Certificate::from_der(end_entity) else {
+			return RevocationStatus::Unknown;
+		};
+
+		if self.ocsp && !ocsp_response.is_empty() {
+			/* the issuer must be in the chain and have signed the certificate.
+			 * Responses for certificates issued directly by a root are ignored */
+			let status = intermediates
+				.iter()
+				.filter_map(|issuer| X509Certificate::from_der(issuer).ok())
+				.find(|(_, issuer)| {
+					is_issued_by(end_entity, issuer.subject().as_raw(), issuer.public_key().raw)
+				})
+				.and_then(|(_, issuer)| check_ocsp(&cert, &issuer, ocsp_response, now.as_secs()));
+
+			match status {
+				Some(RevocationStatus::Unknown) => (),
+				Some(status) => return status,
+				None => warn!(target: self, "== Ignoring invalid stapled OCSP response")
+			}
+		}
+
+		/* webpki already rejected the certificate if the CRL revokes it, but
+		 * an expired CRL says nothing about the certificate's current status
+		 */
+		if self
+			.crl_issuers
+			.iter()
+			.any(|issuer| issuer.covers(cert.issuer().as_raw(), now.as_secs()))
+		{
+			return RevocationStatus::Good;
+		}
+
+		RevocationStatus::Unknown
+	}
+}
+
+impl ServerCertVerifier for RevocationVerifier {
+	fn verify_server_cert(
+		&self, end_entity: &CertificateDer<'_>, intermediates: &[CertificateDer<'_>],
+		server_name: &ServerName<'_>, ocsp_response: &[u8], now: UnixTime
+	) -> Result<ServerCertVerified, rustls::Error> {
+		let verified = match self.inner.verify_server_cert(
+			end_entity,
+			intermediates,
+			server_name,
+			ocsp_response,
+			now
+		) {
+			Err(rustls::Error::InvalidCertificate(CertificateError::Revoked)) => {
+				record_status(RevocationStatus::Revoked);
+
+				return Err(rustls::Error::InvalidCertificate(CertificateError::Revoked));
+			}
+
+			result => result?
+		};
+
+		let status = self.status(end_entity, intermediates, ocsp_response, now);
+
+		record_status(status);
+
+		match (status, self.policy) {
+			(RevocationStatus::Revoked, _) => {
+				warn!(target: self, "== Certificate for {:?} is revoked", server_name);
+
+				Err(rustls::Error::InvalidCertificate(CertificateError::Revoked))
+			}
+
+			(RevocationStatus::Unknown, RevocationPolicy::HardFail) => {
+				warn!(target: self, "== Revocation status unknown for {:?}", server_name);
+
+				Err(rustls::Error::InvalidCertificate(CertificateError::UnknownRevocationStatus))
+			}
+
+			_ => Ok(verified)
+		}
+	}
+
+	fn verify_tls12_signature(
+		&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls12_signature(message, cert, dss)
+	}
+
+	fn verify_tls13_signature(
+		&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		self.inner.verify_tls13_signature(message, cert, dss)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.inner.supported_verify_schemes()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_time() {
+		assert_eq!(parse_time(b"19700101000000Z"), Some(0));
+		assert_eq!(parse_time(b"20000229123456Z"), Some(951_827_696));
+		assert_eq!(parse_time(b"20240101000000.123Z"), Some(1_704_067_200));
+		assert_eq!(parse_time(b"2024010100000Z"), None);
+		assert_eq!(parse_time(b"20240101000000"), None);
+		assert_eq!(parse_time(b"2024O101000000Z"), None);
+		assert_eq!(parse_time(b"19690101000000Z"), None);
+	}
+
+	#[test]
+	fn test_crl_currency() {
+		let crl = CrlIssuer {
+			name: b"issuer".to_vec(),
+			this_update: 1000,
+			next_update: Some(2000)
+		};
+
+		assert!(crl.covers(b"issuer", 1500));
+		assert!(crl.covers(b"issuer", 2000 + CLOCK_SKEW_SECS));
+		assert!(!crl.covers(b"issuer", 2001 + CLOCK_SKEW_SECS));
+		assert!(!crl.covers(b"other", 1500));
+
+		let crl = CrlIssuer { next_update: None, ..crl };
+
+		assert!(crl.covers(b"issuer", u64::from(u32::MAX)));
+	}
+}
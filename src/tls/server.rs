@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::io::{self, IoSlice};
+use std::sync::Arc;
+use std::time::Instant;
+
+use rustls::crypto::ring::sign::any_supported_type;
+use rustls::pki_types::CertificateDer;
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+use rustls::{
+	ConnectionCommon, ProtocolVersion, RootCertStore, ServerConfig, ServerConnection,
+	SupportedCipherSuite
+};
+use xx_core::async_std::io::*;
+use xx_core::debug;
+use xx_core::enumflags2::BitFlags;
+use xx_core::macros::wrapper_functions;
+use xx_core::os::epoll::PollFlag;
+use xx_core::os::socket::Shutdown;
+use xx_pulse::net::*;
+
+use super::*;
+use crate::tls::config::Identity;
+use crate::tls::conn::{
+	handshake, send_close_notify, tls_read, tls_write, SplitTls, TlsInfo, TlsReadHalf,
+	TlsWriteHalf
+};
+use crate::tls::policy::TlsPolicy;
+
+fn certified_key(identity: &Identity) -> Result<Arc<CertifiedKey>> {
+	let key = any_supported_type(identity.key()).map_err(Error::new)?;
+
+	Ok(Arc::new(CertifiedKey::new(identity.certs().to_vec(), key)))
+}
+
+/// Picks a certificate by the server name the client sent with SNI
+#[derive(Debug)]
+struct SniResolver {
+	names: HashMap<String, Arc<CertifiedKey>>,
+	default: Option<Arc<CertifiedKey>>
+}
+
+impl ResolvesServerCert for SniResolver {
+	fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+		client_hello
+			.server_name()
+			.and_then(|name| self.names.get(&name.to_ascii_lowercase()))
+			.or(self.default.as_ref())
+			.cloned()
+	}
+}
+
+/// Builds a [`ServerConfig`] for [`TlsServerConn::accept`]
+#[derive(Clone, Default)]
+pub struct TlsServerConfig {
+	default: Option<Identity>,
+	names: Vec<(String, Identity)>,
+	client_roots: Option<(Arc<RootCertStore>, bool)>,
+	alpn: Vec<Vec<u8>>,
+	policy: TlsPolicy
+}
+
+impl TlsServerConfig {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Present `identity` to clients that don't send a server name, or send
+	/// one without a matching [`TlsServerConfig::sni_identity`]
+	pub fn identity(&mut self, identity: Identity) -> &mut Self {
+		self.default = Some(identity);
+		self
+	}
+
+	/// Present `identity` to clients requesting `name` with SNI
+	pub fn sni_identity(&mut self, name: &str, identity: Identity) -> &mut Self {
+		self.names.push((name.to_ascii_lowercase(), identity));
+		self
+	}
+
+	/// Request a client certificate trusted by `roots`. If `required` is
+	/// false, clients without a certificate are still accepted
+	pub fn verify_clients(&mut self, roots: Arc<RootCertStore>, required: bool) -> &mut Self {
+		self.client_roots = Some((roots, required));
+		self
+	}
+
+	/// Select from `protocols` with ALPN, in order of preference
+	pub fn alpn_protocols(&mut self, protocols: &[&[u8]]) -> &mut Self {
+		self.alpn = protocols.iter().map(|protocol| protocol.to_vec()).collect();
+		self
+	}
+
+	/// Restrict the negotiable TLS versions, cipher suites and key exchange
+	/// groups
+	pub fn policy(&mut self, policy: TlsPolicy) -> &mut Self {
+		self.policy = policy;
+		self
+	}
+
+	pub fn build(&self) -> Result<Arc<ServerConfig>> {
+		if self.default.is_none() && self.names.is_empty() {
+			return Err(fmt_error!("No server certificates configured" @ ErrorKind::InvalidInput));
+		}
+
+		let mut resolver = SniResolver { names: HashMap::new(), default: None };
+
+		if let Some(identity) = &self.default {
+			resolver.default = Some(certified_key(identity)?);
+		}
+
+		for (name, identity) in &self.names {
+			resolver.names.insert(name.clone(), certified_key(identity)?);
+		}
+
+		let builder = self.policy.server_builder()?;
+		let builder = match &self.client_roots {
+			Some((roots, required)) => {
+				let verifier = WebPkiClientVerifier::builder(roots.clone());
+				let verifier = if *required {
+					verifier
+				} else {
+					verifier.allow_unauthenticated()
+				};
+
+				builder.with_client_cert_verifier(verifier.build().map_err(Error::new)?)
+			}
+
+			None => builder.with_no_client_auth()
+		};
+
+		let mut config = builder.with_cert_resolver(Arc::new(resolver));
+
+		config.alpn_protocols.clone_from(&self.alpn);
+
+		Ok(Arc::new(config))
+	}
+}
+
+/// The server side of a TLS connection
+pub struct TlsServerConn {
+	connection: StreamSocket,
+	tls: ServerConnection,
+	allow_truncation: bool,
+	close_notify_sent: bool
+}
+
+#[asynchronous]
+impl TlsServerConn {
+	wrapper_functions! {
+		inner = self.connection;
+
+		#[asynchronous]
+		pub async fn poll(&mut self, flags: BitFlags<PollFlag>) -> Result<BitFlags<PollFlag>>;
+	}
+
+	async fn tls_accept(&mut self) -> Result<()> {
+		let now = Instant::now();
+		let process = ConnectionCommon::process_new_packets;
+
+		/* the alert isn't sent on failure, as the client may never read it */
+		handshake(&mut self.connection, &mut self.tls, process).await?;
+
+		debug!(
+			target: &*self,
+			"== TLS accepted {:?} using {:?} / {:?} ({:.3} ms)",
+			self.server_name(),
+			self.tls.protocol_version(),
+			self.tls.negotiated_cipher_suite(),
+			now.elapsed().as_secs_f32() * 1000.0
+		);
+
+		Ok(())
+	}
+
+	/// Performs the TLS handshake on an accepted connection
+	pub async fn accept(connection: StreamSocket, config: Arc<ServerConfig>) -> Result<Self> {
+		let tls = ServerConnection::new(config).map_err(Error::new)?;
+		let mut connection = Self {
+			connection,
+			tls,
+			allow_truncation: false,
+			close_notify_sent: false
+		};
+
+		connection.tls_accept().await?;
+
+		Ok(connection)
+	}
+
+	/// The parameters negotiated during the handshake. `peer_certificates`
+	/// holds the client's certificates, if one was requested and sent
+	#[must_use]
+	pub fn info(&self) -> TlsInfo {
+		TlsInfo {
+			version: self.protocol_version(),
+			cipher: self.negotiated_cipher_suite().map(|suite| suite.suite()),
+			alpn: self.alpn_protocol().map(<[u8]>::to_vec),
+			peer_certificates: self.peer_certificates().map(<[_]>::to_vec).unwrap_or_default(),
+			revocation: None
+		}
+	}
+
+	/// The server name the client requested with SNI
+	#[must_use]
+	pub fn server_name(&self) -> Option<&str> {
+		self.tls.server_name()
+	}
+
+	#[must_use]
+	pub fn alpn_protocol(&self) -> Option<&[u8]> {
+		self.tls.alpn_protocol()
+	}
+
+	#[must_use]
+	pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+		self.tls.protocol_version()
+	}
+
+	#[must_use]
+	pub fn negotiated_cipher_suite(&self) -> Option<SupportedCipherSuite> {
+		self.tls.negotiated_cipher_suite()
+	}
+
+	/// The certificate chain presented by the client, leaf first
+	#[must_use]
+	pub fn peer_certificates(&self) -> Option<&[CertificateDer<'static>]> {
+		self.tls.peer_certificates()
+	}
+
+	/// Treat EOF without a close_notify from the client as a clean close
+	/// instead of failing with [`TlsError::Truncated`]
+	///
+	/// [`TlsError::Truncated`]: super::error::TlsError::Truncated
+	pub fn set_allow_truncation(&mut self, allow: bool) {
+		self.allow_truncation = allow;
+	}
+
+	/// Sends a close_notify alert, telling the client no more data follows
+	pub async fn send_close_notify(&mut self) -> Result<()> {
+		send_close_notify(&mut self.connection, &mut self.tls, &mut self.close_notify_sent).await
+	}
+
+	pub async fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+		if how != Shutdown::Read {
+			self.send_close_notify().await?;
+		}
+
+		self.connection.shutdown(how).await
+	}
+
+	/// Sends a close_notify and closes the connection. The socket is closed
+	/// even if the alert can't be sent
+	pub async fn close(mut self) -> Result<()> {
+		let result = self.send_close_notify().await;
+
+		self.connection.close().await?;
+
+		result
+	}
+
+	pub async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
+		let read = move |tls: &mut ServerConnection| io::Read::read(&mut tls.reader(), buf);
+
+		tls_read(&mut self.connection, &mut self.tls, self.allow_truncation, read).await
+	}
+
+	pub async fn send(&mut self, buf: &[u8]) -> Result<usize> {
+		let write = |tls: &mut ServerConnection| io::Write::write(&mut tls.writer(), buf);
+
+		tls_write(&mut self.connection, &mut self.tls, write).await
+	}
+
+	pub async fn send_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+		let write =
+			|tls: &mut ServerConnection| io::Write::write_vectored(&mut tls.writer(), bufs);
+
+		tls_write(&mut self.connection, &mut self.tls, write).await
+	}
+}
+
+#[asynchronous]
+impl Read for TlsServerConn {
+	async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		self.recv(buf).await
+	}
+}
+
+#[asynchronous]
+impl Write for TlsServerConn {
+	async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+		self.send(buf).await
+	}
+
+	fn is_write_vectored(&self) -> bool {
+		true
+	}
+
+	async fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+		self.send_vectored(bufs).await
+	}
+}
+
+impl SplitMut for TlsServerConn {
+	type Reader<'a> = TlsReadHalf<'a, ServerConnection>;
+	type Writer<'a> = TlsWriteHalf<'a, ServerConnection>;
+
+	fn try_split(&mut self) -> Result<(Self::Reader<'_>, Self::Writer<'_>)> {
+		let conn = self.connection.try_split()?;
+		let tls = SplitTls::new(&mut self.tls, &mut self.close_notify_sent);
+
+		Ok((
+			TlsReadHalf::new(conn.0, tls.clone(), self.allow_truncation),
+			TlsWriteHalf::new(conn.1, tls)
+		))
+	}
+}
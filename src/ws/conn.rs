@@ -1,9 +1,12 @@
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
 
+use rustls::ServerConfig;
 use xx_pulse::net::*;
 
 use super::stream::Shared;
 use super::*;
+use crate::tls::server::TlsServerConn;
 
 pub type WsReader<'a> = stream::Reader<'a, &'a mut BufReader<HttpConn>>;
 pub type WsFrames<'a> = stream::Frames<'a, &'a mut BufReader<HttpConn>>;
@@ -116,21 +119,36 @@ impl WebSocket {
 
 pub struct WebSocketServer {
 	listener: TcpListener,
-	options: WebSocketOptions
+	options: WebSocketOptions,
+	tls: Option<Arc<ServerConfig>>
 }
 
 pub struct WebSocketHandle {
-	stream: HttpConn,
-	options: WebSocketOptions
+	socket: StreamSocket,
+	options: WebSocketOptions,
+	tls: Option<Arc<ServerConfig>>
 }
 
 #[asynchronous]
 impl WebSocketHandle {
-	async fn accept_websocket(self) -> Result<WebSocket> {
+	async fn handshake(
+		socket: StreamSocket, tls: Option<Arc<ServerConfig>>
+	) -> Result<BufReader<HttpConn>> {
 		struct WsServer {}
 
+		let stream = match tls {
+			Some(config) => HttpConn::new(TlsServerConn::accept(socket, config).await?),
+			None => HttpConn::new(socket)
+		};
+
 		let server = WsServer {};
-		let stream = handle_upgrade(self.stream, &server)
+
+		handle_upgrade(stream, &server).await
+	}
+
+	async fn accept_websocket(self) -> Result<WebSocket> {
+		/* the TLS handshake counts towards the handshake timeout */
+		let stream = Self::handshake(self.socket, self.tls)
 			.timeout(self.options.handshake_timeout)
 			.await
 			.ok_or(WebSocketError::HandshakeTimeout)??;
@@ -156,14 +174,28 @@ impl WebSocketServer {
 	{
 		let listener = Tcp::bind(addrs).await?;
 
-		Ok(Self { listener, options })
+		Ok(Self { listener, options, tls: None })
+	}
+
+	/// Serves `wss://`, performing a TLS handshake with `config` on every
+	/// accepted connection
+	pub async fn bind_tls<A>(
+		addrs: A, options: WebSocketOptions, config: Arc<ServerConfig>
+	) -> Result<Self>
+	where
+		A: ToSocketAddrs
+	{
+		let mut server = Self::bind(addrs, options).await?;
+
+		server.tls = Some(config);
+
+		Ok(server)
 	}
 
 	pub async fn accept(&self) -> Result<WebSocketHandle> {
 		let (socket, _) = self.listener.accept().await?;
-		let stream = HttpConn::new(socket);
 
-		Ok(WebSocketHandle { stream, options: self.options })
+		Ok(WebSocketHandle { socket, options: self.options, tls: self.tls.clone() })
 	}
 
 	pub async fn local_addr(&self) -> Result<SocketAddr> {
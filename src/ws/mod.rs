@@ -15,7 +15,9 @@ use xx_pulse::impls::TaskExt;
 use super::*;
 use crate::http::stream::*;
 use crate::http::transfer::Request;
-use crate::http::{Headers, HttpError, Payload, TryIntoHeaderName, TryIntoHeaderValue, Version};
+use crate::http::{
+	Headers, HttpError, ParseMode, Payload, TryIntoHeaderName, TryIntoHeaderValue, Version
+};
 
 mod conn;
 mod consts;
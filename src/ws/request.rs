@@ -1,3 +1,7 @@
+use std::sync::Arc;
+
+use rustls::ClientConfig;
+
 use super::*;
 use crate::net::conn::IpStrategy;
 
@@ -68,6 +72,22 @@ impl WsRequest {
 
 		#[allow(clippy::impl_trait_in_params)]
 		pub fn payload(&mut self, payload: impl Into<Payload>) -> &mut Self;
+
+		pub fn basic_auth(&mut self, username: &str, password: &str) -> &mut Self;
+
+		pub fn bearer_auth(&mut self, token: &str) -> &mut Self;
+
+		pub fn digest_auth(&mut self, username: &str, password: &str) -> &mut Self;
+
+		pub fn set_tls_config(&mut self, config: Arc<ClientConfig>) -> &mut Self;
+
+		pub fn danger_accept_invalid_certs(&mut self, accept: bool) -> &mut Self;
+
+		pub fn danger_accept_invalid_hostnames(&mut self, accept: bool) -> &mut Self;
+
+		pub fn set_early_data(&mut self, enable: bool) -> &mut Self;
+
+		pub fn allow_tls_truncation(&mut self, allow: bool) -> &mut Self;
 	}
 
 	pub async fn run(&mut self) -> Result<WebSocket> {
@@ -109,9 +109,12 @@ async fn handle_request<T>(reader: &mut impl BufRead, log: &T) -> Result<Headers
 	let mut headers = Headers::new();
 
 	match (DEFAULT_MAXIMUM_HEADER_SIZE as usize).checked_sub(total_size) {
-		Some(limit) => read_headers_limited(reader, &mut headers, limit, log).await?,
+		Some(limit) => {
+			read_headers_limited(reader, &mut headers, limit, ParseMode::Strict, log).await?
+		}
+
 		None => return Err(HttpError::HeadersTooLong.into())
-	}
+	};
 
 	Ok(headers)
 }